@@ -0,0 +1,194 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use svg_gal::gall_fn::Layout;
+
+#[derive(Parser)]
+#[command(name = "svg_gal", version, about = "Sherman's Circular Gallifreyan generator")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    //lets `svg_gal hello world` work without naming the subcommand
+    #[command(flatten)]
+    pub render: RenderArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render a phrase to an image file
+    Render(RenderArgs),
+    /// Print the phrase an SVG made by svg_gal says
+    Decode(DecodeArgs),
+}
+
+#[derive(Args, Clone)]
+pub struct DecodeArgs {
+    /// SVG file to read, or "-" for stdin
+    pub file: PathBuf,
+    /// Fail unless the file says this phrase, read with the same config as a render
+    #[arg(long, value_name = "PHRASE")]
+    pub expect: Option<String>,
+    /// TOML or JSON config file, as for render
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// Override a single config value, as for render
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct RenderArgs {
+    /// Phrase to render, either quoted or as separate words. Read from stdin if omitted or "-"
+    pub phrase: Vec<String>,
+    /// Output file path. Takes priority over --out-dir
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Directory to save into, using a filename derived from the phrase
+    #[arg(short = 'd', long, default_value = "SVGs")]
+    pub out_dir: PathBuf,
+    /// Output file format
+    #[arg(short, long, value_enum, default_value_t = Format::Svg)]
+    pub format: Format,
+    /// TOML or JSON file overriding the default style & layout settings
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// Override a single config value, e.g. `--set stack=false` (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// How the words are arranged inside the sentence circle
+    #[arg(short, long, value_parser = PossibleValuesParser::new(Layout::NAMES))]
+    pub layout: Option<String>,
+    /// Seed for the dash line layout, recorded in the output file. A random one is picked (and printed) if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Width of the output image in pixels, height follows the aspect ratio of the drawn view
+    #[arg(short, long, conflicts_with = "dpi", value_parser = clap::value_parser!(u32).range(1..))]
+    pub size: Option<u32>,
+    /// Scale the drawn view as if printed at this DPI (96 keeps 1 unit = 1 pixel)
+    #[arg(long, value_parser = positive_dpi)]
+    pub dpi: Option<f32>,
+    /// Leave out the canvas background
+    #[arg(short, long)]
+    pub transparent: bool,
+    /// Write words by how they sound rather than how they are spelt
+    #[arg(long)]
+    pub phonetic: bool,
+    /// Draw single stroke centre lines for a pen plotter, see `pen_width` in the config
+    #[arg(long)]
+    pub line_art: bool,
+    /// Only print errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Print extra layout information
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Format {
+    Svg,
+    Png,
+    /// Pen plotter G-code, see the plot_* and gcode_* config settings
+    Gcode,
+    /// DXF for CAD and laser cutters, see the plot_* and dxf_outlines config settings
+    Dxf,
+}
+
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Gcode => "gcode",
+            Format::Dxf => "dxf",
+        }
+    }
+}
+
+impl Verbosity {
+    pub fn info(&self, message: &str) {
+        if *self >= Verbosity::Normal {
+            println!("{}", message)
+        }
+    }
+    pub fn debug(&self, message: &str) {
+        if *self >= Verbosity::Verbose {
+            println!("{}", message)
+        }
+    }
+}
+
+impl RenderArgs {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+    //Splits the phrase into words, falling back to stdin
+    pub fn words(&self) -> io::Result<Vec<String>> {
+        let from_stdin = self.phrase.is_empty() || (self.phrase.len() == 1 && self.phrase[0] == "-");
+        let raw = if from_stdin {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        } else {
+            self.phrase.join(" ")
+        };
+        Ok(raw.split_whitespace().map(str::to_string).collect())
+    }
+    pub fn output_path(&self, words: &[String]) -> PathBuf {
+        match &self.output {
+            Some(path) => path.clone(),
+            None => self.out_dir.join(file_stem(words)).with_extension(self.format.extension()),
+        }
+    }
+}
+
+impl DecodeArgs {
+    pub fn read(&self) -> io::Result<String> {
+        if self.file.as_os_str() == "-" {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            Ok(buffer)
+        } else {
+            std::fs::read_to_string(&self.file)
+        }
+    }
+}
+
+fn positive_dpi(raw:&str) -> Result<f32, String> {
+    match raw.parse::<f32>() {
+        Ok(dpi) if dpi > 0.0 && dpi.is_finite() => Ok(dpi),
+        Ok(_) => Err("DPI must be above 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+//Keeps characters that are safe in a filename on every platform and puts
+//'_' for the rest, so "3.14" and "314" don't overwrite each other
+pub fn file_stem(words: &[String]) -> String {
+    let stem: String = words
+        .concat()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' {c} else {'_'})
+        .collect();
+    if stem.is_empty() {
+        "untitled".to_string()
+    } else {
+        stem
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::{Display, Write};
+
+use crate::gall_config::Config;
+use crate::plot::{self, Layer, Point, Segment, Shape, Sheet, Stroke};
+use crate::render::ViewBox;
+
+//Arcs and lines shorter than this (in mm) are left out, as CAD reads an arc
+//that ends where it starts as a full circle
+const MIN_LENGTH: f64 = 0.001;
+
+//AutoCAD colour numbers, so cutter software can tell the layers apart
+fn layer_colour(layer:Layer) -> u8 {
+    match layer {
+        Layer::Sentence => 7,
+        Layer::Words => 5,
+        Layer::Letters => 1,
+        Layer::Vowels => 3,
+        Layer::Decorations => 6,
+        Layer::Connectors => 4,
+    }
+}
+
+//An R12 ASCII DXF with y up, one named layer per part of the drawing. Circles
+//stay circles, arcs become ARCs and curved connectors short LINEs. R12 has no
+//units header ($INSUNITS needs AC1015), so units are millimetres by convention.
+pub fn write(strokes:Vec<Stroke>, view:ViewBox, phrase:&str, seed:u64, config:&Config) -> String {
+    let sheet = Sheet::upright(view, config);
+    let mut dxf = String::new();
+    let comment:String = phrase.chars().filter(|cha| !cha.is_control()).collect();
+    pair(&mut dxf, 999, format!("svg_gal: {}, seed {}", comment, seed));
+    section(&mut dxf, "HEADER");
+    pair(&mut dxf, 9, "$ACADVER");
+    pair(&mut dxf, 1, "AC1009");
+    pair(&mut dxf, 0, "ENDSEC");
+    section(&mut dxf, "TABLES");
+    table(&mut dxf, "LTYPE", 1);
+    entity(&mut dxf, "LTYPE", None);
+    for (code, value) in [(2, "CONTINUOUS"), (70, "0"), (3, "Solid line"), (72, "65"), (73, "0"), (40, "0.0")] {
+        pair(&mut dxf, code, value);
+    }
+    pair(&mut dxf, 0, "ENDTAB");
+    table(&mut dxf, "LAYER", Layer::ALL.len());
+    for layer in Layer::ALL {
+        entity(&mut dxf, "LAYER", None);
+        pair(&mut dxf, 2, layer.name());
+        pair(&mut dxf, 70, 0);
+        pair(&mut dxf, 62, layer_colour(layer));
+        pair(&mut dxf, 6, "CONTINUOUS");
+    }
+    pair(&mut dxf, 0, "ENDTAB");
+    pair(&mut dxf, 0, "ENDSEC");
+    section(&mut dxf, "ENTITIES");
+    for stroke in &strokes {
+        shape(&mut dxf, &sheet, stroke);
+    }
+    pair(&mut dxf, 0, "ENDSEC");
+    pair(&mut dxf, 0, "EOF");
+    dxf
+}
+
+fn shape(dxf:&mut String, sheet:&Sheet, stroke:&Stroke) {
+    let layer = Some(stroke.layer);
+    match &stroke.shape {
+        Shape::Circle(centre, radius) => {
+            entity(dxf, "CIRCLE", layer);
+            point(dxf, 10, sheet.place(*centre));
+            pair(dxf, 40, format!("{:.4}", radius*sheet.scale));
+        },
+        Shape::Path(start, segments) => {
+            let mut pen = *start;
+            for segment in segments {
+                let end = segment.end();
+                match *segment {
+                    Segment::Line(_) => line(dxf, sheet, layer, pen, end),
+                    Segment::Arc{centre, clockwise, ..} => arc(dxf, sheet, layer, (pen, centre, end), clockwise),
+                    Segment::Quad(control, _) => {
+                        let mut from = pen;
+                        for to in plot::quad_lines(pen, control, end, sheet.scale) {
+                            line(dxf, sheet, layer, from, to);
+                            from = to;
+                        }
+                    },
+                }
+                pen = end;
+            }
+            //a dot smaller than the pen is a path that goes nowhere
+            if segments.iter().all(|segment| *segment == Segment::Line(*start)) {
+                entity(dxf, "POINT", layer);
+                point(dxf, 10, sheet.place(*start));
+            }
+        },
+    }
+}
+
+fn line(dxf:&mut String, sheet:&Sheet, layer:Option<Layer>, start:Point, end:Point) {
+    let (start, end) = (sheet.place(start), sheet.place(end));
+    if plot::dist(start, end) < MIN_LENGTH {
+        return
+    }
+    entity(dxf, "LINE", layer);
+    point(dxf, 10, start);
+    point(dxf, 11, end);
+}
+
+//DXF arcs always turn anticlockwise from their start angle, so clockwise
+//arcs are written from their end
+fn arc(dxf:&mut String, sheet:&Sheet, layer:Option<Layer>, (start, centre, end):(Point,Point,Point), clockwise:bool) {
+    let (start, centre, end) = (sheet.place(start), sheet.place(centre), sheet.place(end));
+    if plot::dist(start, end) < MIN_LENGTH {
+        return
+    }
+    let degrees = |point:Point| (point.1 - centre.1).atan2(point.0 - centre.0).to_degrees().rem_euclid(360.0);
+    let (from, to) = if sheet.clockwise(clockwise) {(end, start)} else {(start, end)};
+    entity(dxf, "ARC", layer);
+    point(dxf, 10, centre);
+    pair(dxf, 40, format!("{:.4}", plot::dist(centre, start)));
+    pair(dxf, 50, format!("{:.4}", degrees(from)));
+    pair(dxf, 51, format!("{:.4}", degrees(to)));
+}
+
+fn section(dxf:&mut String, name:&str) {
+    pair(dxf, 0, "SECTION");
+    pair(dxf, 2, name);
+}
+fn table(dxf:&mut String, name:&str, entries:usize) {
+    pair(dxf, 0, "TABLE");
+    pair(dxf, 2, name);
+    pair(dxf, 70, entries);
+}
+fn entity(dxf:&mut String, kind:&str, layer:Option<Layer>) {
+    pair(dxf, 0, kind);
+    if let Some(layer) = layer {
+        pair(dxf, 8, layer.name());
+    }
+}
+//x, y and z under `code`, `code` + 10 and `code` + 20
+fn point(dxf:&mut String, code:u16, (x, y):Point) {
+    pair(dxf, code, format!("{:.4}", x));
+    pair(dxf, code + 10, format!("{:.4}", y));
+    pair(dxf, code + 20, "0.0");
+}
+fn pair(dxf:&mut String, code:u16, value:impl Display) {
+    let _ = writeln!(dxf, "{:>3}\n{}", code, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Group code and value pairs, as a DXF reader sees them
+    fn pairs(dxf:&str) -> Vec<(u16, &str)> {
+        let lines:Vec<&str> = dxf.lines().collect();
+        lines.chunks(2).map(|pair| (pair[0].trim().parse().unwrap(), pair[1])).collect()
+    }
+
+    #[test]
+    fn writes_layers_and_entities() {
+        let config = Config::default();
+        let sent = crate::layout("hello world 42", &config).unwrap();
+        let (dxf, _) = crate::draw_dxf(sent, &config, 1);
+        let pairs = pairs(&dxf);
+        assert_eq!(pairs.last(), Some(&(0, "EOF")));
+        let sections:Vec<&str> = pairs.windows(2)
+            .filter(|win| win[0] == (0, "SECTION"))
+            .map(|win| win[1].1)
+            .collect();
+        assert_eq!(sections, ["HEADER", "TABLES", "ENTITIES"]);
+        let layers:Vec<&str> = pairs.windows(2)
+            .filter(|win| win[0] == (0, "LAYER"))
+            .map(|win| win[1].1)
+            .collect();
+        assert_eq!(layers, Layer::ALL.map(|layer| layer.name()));
+        //every entity sits on one of those layers
+        let entities = pairs.iter().skip_while(|&&pair| pair != (2, "ENTITIES"));
+        let mut kinds:Vec<&str> = Vec::new();
+        let mut last = "";
+        for &(code, value) in entities {
+            match code {
+                0 => last = value,
+                8 => {
+                    assert!(layers.contains(&value), "{} on unknown layer {}", last, value);
+                    kinds.push(last);
+                },
+                _ => {},
+            }
+        }
+        for kind in ["CIRCLE", "ARC", "LINE"] {
+            assert!(kinds.contains(&kind), "no {} written", kind);
+        }
+    }
+}
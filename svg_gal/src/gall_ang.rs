@@ -13,14 +13,15 @@ pub fn svg_ang2gall_ang(svg_ang:f64) -> f64{
     constrain(-svg_ang+PI/2.0)
 }
 pub fn gall_ang2svg_ang(gall_ang:f64) -> f64{
-    (PI/2.0-gall_ang)
+    PI/2.0-gall_ang
 }
 pub fn constrain_opt(angle:Option<f64>) -> Option<f64> {
-    if let Some(ang) = angle {
-        Some(constrain(ang))
-    } else {
-        None
-    }
+    angle.map(constrain)
+}
+//Smallest angle between two directions, 0 to PI
+pub fn diff(ang1:f64, ang2:f64) -> f64 {
+    let gap = constrain(ang1 - ang2);
+    gap.min(TAU - gap)
 }
 pub fn constrain(mut ang:f64) -> f64 {
     while ang >= TAU {
@@ -43,7 +44,10 @@ impl GallAng {
     }
     pub fn rotate(&mut self, angle:f64) -> Result<(),Error>{
         match self.angle {
-            Some(ang) => Ok(self.mut_ang(Some(ang + angle))),
+            Some(ang) => {
+                self.mut_ang(Some(ang + angle));
+                Ok(())
+            },
             None => Err(Error::new(GallError::AngleUndefined))
         } 
     }
@@ -1,36 +1,265 @@
+use std::fs;
+use std::path::Path;
 
-pub struct Config {}
+use serde::{Deserialize, Serialize};
+
+use crate::gall_errors::{Error, GallError};
+use crate::gall_fn::{Layout, MAX_WORD_RADIUS};
+use crate::gall_orth::Orthography;
+use crate::plot::{DotFill, PlotOrigin};
+use crate::routing::ConnectorMode;
+
+//Field names mirror the old compile-time constants, lowercased.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub enable_canvas: bool,
+    pub node_visibility: bool,
+    pub layout: Layout,
+    pub width: f64,
+    pub height: f64,
+    pub stack: bool,
+    pub orthography: String,
+    pub disabled_digraphs: Vec<String>,
+    pub phonetic: bool,
+    pub pronunciations: String,
+    pub auto_radius: bool,
+    pub fit_canvas: bool,
+    pub canvas_margin: f64,
+    pub accessible: bool,
+    pub svg_title: String,
+    pub svg_description: String,
+    pub line_art: bool,
+    pub pen_width: f64,
+    pub pen_colour: String,
+    pub dot_fill: DotFill,
+    pub plot_width_mm: f64,
+    pub plot_margin_mm: f64,
+    pub plot_origin: PlotOrigin,
+    pub gcode_pen_up: String,
+    pub gcode_pen_down: String,
+    pub gcode_feed: f64,
+    pub gcode_travel_feed: f64,
+    pub dxf_outlines: bool,
+    pub sent_radius: f64,
+    pub sent_thick: f64,
+    pub letter_frac_of_wrd: f64,
+    pub letter_thick_frac: f64,
+    pub digit_thick_frac: f64,
+    pub stack_sep_dist: f64,
+    pub num_sep_dist: f64,
+    pub consec_lett_growth: f64,
+    pub word_gap: f64,
+    pub vowel_frac_of_wrd: f64,
+    pub vowel_thick_frac: f64,
+    pub dot_radius: f64,
+    pub def_dot_spread: f64,
+    pub def_pair_thick: i16,
+    pub pair_cross_weight: f64,
+    pub pair_obstacle_weight: f64,
+    pub connector_mode: ConnectorMode,
+    pub punct_radius: f64,
+    pub debug_colour: String,
+    pub canvas_colour: String,
+    pub sent_colour: String,
+    pub wrd_colour: String,
+    pub dot_colour: String,
+    pub jz_colour: String,
+    //digit rings and the strokes across them
+    pub digit_colour: String,
+    pub vow_colour: String,
+    pub skel_colour: String,
+    pub sent_skel_colour: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enable_canvas: true,
+            node_visibility: true,
+            layout: Layout::Ring,
+            width: 2048.0,
+            height: 2048.0,
+            stack: true,
+            orthography: "english".to_string(),
+            disabled_digraphs: Vec::new(),
+            phonetic: false,
+            pronunciations: String::new(),
+            auto_radius: true,
+            fit_canvas: true,
+            canvas_margin: 40.0,
+            accessible: true,
+            svg_title: String::new(),
+            svg_description: String::new(),
+            line_art: false,
+            pen_width: 4.0,
+            pen_colour: "black".to_string(),
+            dot_fill: DotFill::Hatch,
+            plot_width_mm: 200.0,
+            plot_margin_mm: 10.0,
+            plot_origin: PlotOrigin::BottomLeft,
+            gcode_pen_up: "G0 Z5".to_string(),
+            gcode_pen_down: "G1 Z0 F1000".to_string(),
+            gcode_feed: 1500.0,
+            gcode_travel_feed: 0.0,
+            dxf_outlines: true,
+            sent_radius: 1020.0,
+            sent_thick: 20.0,
+            letter_frac_of_wrd: 0.35,
+            letter_thick_frac: 0.6,
+            digit_thick_frac: 0.4,
+            stack_sep_dist: 25.0,
+            num_sep_dist: 25.0,
+            consec_lett_growth: 15.0,
+            word_gap: 15.0,
+            vowel_frac_of_wrd: 0.15,
+            vowel_thick_frac: 0.45,
+            dot_radius: 25.0,
+            def_dot_spread: 0.6,
+            def_pair_thick: 4,
+            pair_cross_weight: 400.0,
+            pair_obstacle_weight: 600.0,
+            connector_mode: ConnectorMode::Straight,
+            punct_radius: 45.0,
+            debug_colour: "purple".to_string(),
+            canvas_colour: "yellow".to_string(),
+            sent_colour: "orange".to_string(),
+            wrd_colour: "green".to_string(),
+            dot_colour: "blue".to_string(),
+            jz_colour: "black".to_string(),
+            digit_colour: "black".to_string(),
+            vow_colour: "red".to_string(),
+            skel_colour: "black".to_string(),
+            sent_skel_colour: "black".to_string(),
+        }
+    }
+}
 
 impl Config {
-    pub const ENABLE_CANVAS: bool = true;
-    pub const NODE_VISIBILITY: bool = true;
-    pub const BASIC_LAYOUT: bool = true;
-    pub const WIDTH:f64 = 2048.0;
-    pub const HEIGHT:f64 = 2048.0;
-    pub const STACK: bool = true;
-    pub const SENT_RADIUS: f64 = 1020.0;
-    pub const SENT_THICK: f64 = 20.0;
-    pub const LETTER_FRAC_OF_WRD: f64 = 0.35;
-    pub const LETTER_THICK_FRAC: f64 = 0.6;
-    pub const DIGIT_THICK_FRAC: f64 = 0.4;
-    pub const STACK_SEP_DIST: f64 = 25.0;
-    pub const NUM_SEP_DIST: f64 = 25.0;
-    pub const CONSEC_LETT_GROWTH: f64 = 15.0;
-    pub const VOWEL_FRAC_OF_WRD : f64 = 0.15;
-    pub const VOWEL_THICK_FRAC: f64 = 0.45;
-    pub const DOT_RADIUS: f64 = 25.0;
-    pub const DEF_DOT_SPREAD: f64 = 0.6;
-    pub const DEF_PAIR_THICK: i16 = 4;
+    //Numerical tolerances, not meant to be tuned per render
     pub const COLLISION_DIST: f64 = 0.0001;
     pub const STEP_DIST: f64 = 2.0 * Config::COLLISION_DIST;
-    pub const fn DEBUG_COLOUR() -> &'static str {"purple"}
-    pub const fn CANVAS_COLOUR() -> &'static str {"yellow"}
-    pub const fn SENT_COLOUR() -> &'static str {"orange"}
-    pub const fn WRD_COLOUR() -> &'static str {"green"}
-    pub const fn DOT_COLOUR() -> &'static str {"blue"}
-    pub const fn JZ_COLOUR() -> &'static str {"black"}
-    pub const fn VOW_COLOUR() -> &'static str {"red"}
-    pub const fn SKEL_COLOUR() -> &'static str {"black"}
-    pub const fn SENT_SKEL_COLOUR() -> &'static str {"black"}
+
+    //Loads a TOML (or .json) file, applies `key=value` overrides on top, then validates.
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> Result<Config, Error> {
+        let mut table = match path {
+            Some(path) => read_table(path)?,
+            None => toml::Table::new(),
+        };
+        for item in overrides {
+            let (key, value) = parse_override(item)?;
+            table.insert(key, value);
+        }
+        let config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| invalid(e.message()))?;
+        config.validate()?;
+        Ok(config)
+    }
+    pub fn validate(&self) -> Result<(), Error> {
+        check_positive("width", self.width)?;
+        check_positive("height", self.height)?;
+        check_positive("sent_radius", self.sent_radius)?;
+        check_range("canvas_margin", self.canvas_margin, 0.0, f64::MAX)?;
+        check_range("sent_thick", self.sent_thick, 0.0, self.sent_radius/4.0)?;
+        check_range("word_gap", self.word_gap, 0.0, self.sent_radius/4.0)?;
+        check_positive("punct_radius", self.punct_radius)?;
+        check_range("punct_radius", self.punct_radius, 0.0, self.sent_radius/4.0)?;
+        //b row letters reach 1.2 word radii out, so smaller ones miss the word line
+        check_range("letter_frac_of_wrd", self.letter_frac_of_wrd, 0.15, 0.7)?;
+        check_range("letter_thick_frac", self.letter_thick_frac, 0.0, 1.0)?;
+        check_range("digit_thick_frac", self.digit_thick_frac, 0.0, 1.0)?;
+        check_positive("vowel_frac_of_wrd", self.vowel_frac_of_wrd)?;
+        check_range("vowel_frac_of_wrd", self.vowel_frac_of_wrd, 0.0, 0.5)?;
+        check_range("vowel_thick_frac", self.vowel_thick_frac, 0.0, 1.0)?;
+        //the absolute sizes have to fit the letters of the largest word the sentence holds
+        let letter_radius = MAX_WORD_RADIUS.min(self.sent_radius)*self.letter_frac_of_wrd;
+        check_positive("dot_radius", self.dot_radius)?;
+        check_range("dot_radius", self.dot_radius, 0.0, letter_radius/4.0)?;
+        check_range("stack_sep_dist", self.stack_sep_dist, 0.0, letter_radius/2.0)?;
+        check_range("num_sep_dist", self.num_sep_dist, 0.0, letter_radius/2.0)?;
+        check_range("consec_lett_growth", self.consec_lett_growth, 0.0, letter_radius/4.0)?;
+        check_positive("pen_width", self.pen_width)?;
+        check_positive("plot_width_mm", self.plot_width_mm)?;
+        check_range("plot_margin_mm", self.plot_margin_mm, 0.0, f64::MAX)?;
+        check_positive("gcode_feed", self.gcode_feed)?;
+        check_range("gcode_travel_feed", self.gcode_travel_feed, 0.0, f64::MAX)?;
+        check_range("pair_cross_weight", self.pair_cross_weight, 0.0, f64::MAX)?;
+        check_range("pair_obstacle_weight", self.pair_obstacle_weight, 0.0, f64::MAX)?;
+        check_range("def_dot_spread", self.def_dot_spread, 0.0, std::f64::consts::PI)?;
+        Orthography::from_config(self)?;
+        if self.def_pair_thick <= 0 {
+            return Err(invalid("def_pair_thick must be greater than 0"));
+        }
+        for (name, colour) in [
+            ("debug_colour", &self.debug_colour),
+            ("canvas_colour", &self.canvas_colour),
+            ("sent_colour", &self.sent_colour),
+            ("wrd_colour", &self.wrd_colour),
+            ("dot_colour", &self.dot_colour),
+            ("jz_colour", &self.jz_colour),
+            ("digit_colour", &self.digit_colour),
+            ("vow_colour", &self.vow_colour),
+            ("skel_colour", &self.skel_colour),
+            ("sent_skel_colour", &self.sent_skel_colour),
+            ("pen_colour", &self.pen_colour),
+        ] {
+            if colour.trim().is_empty() {
+                return Err(invalid(&format!("{} must not be empty", name)));
+            }
+        }
+        for (name, command) in [
+            ("gcode_pen_up", &self.gcode_pen_up),
+            ("gcode_pen_down", &self.gcode_pen_down),
+        ] {
+            if command.trim().is_empty() {
+                return Err(invalid(&format!("{} must not be empty", name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::new(GallError::ConfigRead(format!("{}: {}", path.display(), e))))?;
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        serde_json::from_str(&text).map_err(|e| invalid(&e.to_string()))
+    } else {
+        text.parse().map_err(|e: toml::de::Error| invalid(e.message()))
+    }
 }
 
+//Values are read as TOML, so `stack=false` is a bool and `wrd_colour=green` falls back to a string
+fn parse_override(item: &str) -> Result<(String, toml::Value), Error> {
+    let (key, raw) = item
+        .split_once('=')
+        .ok_or_else(|| invalid(&format!("override `{}` is not of the form key=value", item)))?;
+    let raw = raw.trim();
+    let value = match format!("v = {}", raw).parse::<toml::Table>() {
+        Ok(mut table) => table.remove("v").unwrap_or(toml::Value::String(raw.to_string())),
+        Err(_) => toml::Value::String(raw.to_string()),
+    };
+    Ok((key.trim().to_string(), value))
+}
+
+fn check_positive(name: &str, value: f64) -> Result<(), Error> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(&format!("{} must be greater than 0, got {}", name, value)))
+    }
+}
+
+fn check_range(name: &str, value: f64, low: f64, high: f64) -> Result<(), Error> {
+    if value >= low && value <= high {
+        Ok(())
+    } else {
+        Err(invalid(&format!("{} must be between {} and {}, got {}", name, low, high, value)))
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(GallError::InvalidConfig(message.to_string()))
+}
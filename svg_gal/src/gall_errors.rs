@@ -22,8 +22,15 @@ pub enum GallError {
     DoNotMutTainer ,
     NoStepSpace ,
     NoStemInTainer ,
+    NoVowelInTainer,
     LetterNotTouchingSkel,
     TainerNotInit,
+    EmptyPhrase,
+    NoLetters,
+    ConfigRead(String),
+    InvalidConfig(String),
+    ExportFailed(String),
+    DecodeFailed(String),
 }
 
 impl Error {
@@ -51,9 +58,15 @@ impl fmt::Display for Error {
             GallError::DoNotMutTainer =>"Do Not Mut Tainer",
             GallError::NoStepSpace =>"Stepping further will cross 6 o'clock",
             GallError::NoStemInTainer =>"Tainer stemtype is None",
+            GallError::NoVowelInTainer =>"Tainer has no vowel",
             GallError::LetterNotTouchingSkel=>"Letter is not touching skeleton",
             GallError::TainerNotInit=>"Tainer has not been initialised yet",
-            _ => "Unspecified Error",
+            GallError::EmptyPhrase=>"No phrase given",
+            GallError::NoLetters=>"Phrase has no drawable letters",
+            GallError::ConfigRead(detail) => return write!(f, "Could not read config file {}", detail),
+            GallError::InvalidConfig(detail) => return write!(f, "Invalid config: {}", detail),
+            GallError::ExportFailed(detail) => return write!(f, "Export failed: {}", detail),
+            GallError::DecodeFailed(detail) => return write!(f, "Could not decode: {}", detail),
         };
         write!(f, "{}",message)
     }
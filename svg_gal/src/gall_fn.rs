@@ -1,6 +1,16 @@
 use std::f64::consts::{PI, TAU};
+use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
+
+use crate::gall_config::Config;
+use crate::gall_ang;
 use crate::gall_errors::{Error, GallError};
+use crate::gall_mark::PunctType;
+use crate::gall_lex::{self, Token};
+use crate::gall_num::GallNumber;
+use crate::gall_orth::Orthography;
+use crate::gall_phon::{self, Pronouncer};
 use crate::gall_stem::StemType; 
 use crate::gall_vowel::VowelType;
 
@@ -9,46 +19,203 @@ pub enum LetterMark {
     Stem(StemType),
     GallVowel(VowelType),
     GallMark,
-    Digit(i8), //TODO: change to i32
+    Number(GallNumber),
 }
 #[derive(PartialEq)]
 pub enum Decor {
     Dot,
     Dash,    
 }
+#[derive(Clone, Copy)]
 pub enum Size {
     Small,
     Med,
     Large,
 }
+//How the words of a sentence are arranged inside it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    //Fixed presets for up to four words, an even ring beyond that
+    #[default]
+    Ring,
+    //Outward from the middle along an Archimedean spiral
+    Spiral,
+    //Filled ring by ring from the middle outwards
+    Concentric,
+    //One ring, with room and size given by each word's weight
+    Weighted,
+    //First word in the middle, the rest in a ring around it
+    Central,
+}
+#[derive(Clone)]
 pub struct ProcessedWord {
     pub word: String,
+    pub tokens: Vec<Token>,
     pub length: usize,
     pub vowels: usize,
     pub size: Size,
     pub a_flag: bool,
     pub z_stem: bool,
     pub s_stem: bool,
-    pub neg_digit: Vec<bool>,
+    pub punct_before: Vec<PunctType>,
+    //marks between letters, like the apostrophe of "don't", with how many
+    //letters come before each. They are drawn with punct_after.
+    pub punct_inside: Vec<(usize, PunctType)>,
+    pub punct_after: Vec<PunctType>,
+    //spelt by sound, with the letters of gall_phon::SOUNDS
+    pub spoken: bool,
+    //false draws repeated stems side by side even with `stack` on, for
+    //stacks too deep to draw on the word line
+    pub stack: bool,
 } 
-pub fn basic_angle(word_list:&Vec<ProcessedWord>, small_weight:i32,avg_weight:i32,large_weight:i32) -> f64 {
-    let mut pool = 0;
-    for word in word_list {
-        match word.size {
-            Size::Large => pool += large_weight,
-            Size::Med => pool += avg_weight,
-            Size::Small => pool += small_weight,
-        }
-        if word.a_flag {
-            pool += 2
-        }
-        if word.z_stem {
-            pool += 3
-        }
+pub fn word_weight(word:&ProcessedWord, small_weight:i32,avg_weight:i32,large_weight:i32) -> i32 {
+    let mut weight = match word.size {
+        Size::Large => large_weight,
+        Size::Med => avg_weight,
+        Size::Small => small_weight,
+    };
+    if word.a_flag {
+        weight += 2
+    }
+    if word.z_stem {
+        weight += 3
     }
+    weight
+}
+pub fn basic_angle(word_list:&[ProcessedWord], small_weight:i32,avg_weight:i32,large_weight:i32) -> f64 {
+    let pool:i32 = word_list.iter()
+        .map(|word| word_weight(word, small_weight, avg_weight, large_weight))
+        .sum();
     TAU/f64::from(pool) 
 } 
 
+impl Layout {
+    pub const NAMES: [&'static str; 5] = ["ring", "spiral", "concentric", "weighted", "central"];
+}
+impl FromStr for Layout {
+    type Err = Error;
+    fn from_str(name:&str) -> Result<Layout, Error> {
+        match name {
+            "ring" => Ok(Layout::Ring),
+            "spiral" => Ok(Layout::Spiral),
+            "concentric" => Ok(Layout::Concentric),
+            "weighted" => Ok(Layout::Weighted),
+            "central" => Ok(Layout::Central),
+            _ => Err(Error::new(GallError::InvalidConfig(format!(
+                "unknown layout `{}`, expected one of {}", name, Layout::NAMES.join(", ")
+            )))),
+        }
+    }
+}
+
+//Gap left between neighbouring words, as a fraction of the word diameter
+const WORD_GAP: f64 = 1.1;
+pub const MAX_WORD_RADIUS: f64 = 650.0;
+//Smallest word radius the ring preset starts a long phrase at
+const MIN_RING_RADIUS: f64 = 45.0;
+
+//word_radius, word_thick, word_angle, word_dist for every word of the sentence
+pub fn word_layouts(layout:Layout, word_list:&[ProcessedWord], config:&Config) -> Vec<(f64,f64,f64,f64)> {
+    let len = word_list.len();
+    //room inside the sentence ring, layouts other than Ring scale with it
+    let room = config.sent_radius - 2.0*config.sent_thick;
+    match layout {
+        _ if len <= 1 => vec![default_layouts(len, 0)],
+        Layout::Ring => (0..len).map(|num| default_layouts(len, num)).collect(),
+        Layout::Spiral => spiral_layout(len, room),
+        Layout::Concentric => concentric_layout(len, room),
+        Layout::Weighted => weighted_layout(word_list, room),
+        Layout::Central => central_layout(len, room),
+    }
+}
+
+fn word_thick(radius:f64) -> f64 {
+    radius/12.0
+}
+
+//Keeps shrinking the words until `place` fits them all inside `room`
+fn shrink_to_fit<F>(len:usize, room:f64, place:F) -> Vec<(f64,f64,f64,f64)>
+where F: Fn(f64) -> Vec<(f64,f64)> {
+    let mut radius = (room*(0.5/len as f64).sqrt()).min(MAX_WORD_RADIUS);
+    loop {
+        let spots = place(radius);
+        let outer = spots.iter().map(|&(_, dist)| dist).fold(0.0, f64::max) + radius;
+        if outer <= room || radius < 1.0 {
+            return spots.into_iter()
+                .map(|(ang, dist)| (radius, word_thick(radius), gall_ang::constrain(ang), dist))
+                .collect()
+        }
+        radius *= 0.95;
+    }
+}
+
+fn spiral_layout(len:usize, room:f64) -> Vec<(f64,f64,f64,f64)> {
+    shrink_to_fit(len, room, |radius| {
+        let spacing = 2.0*WORD_GAP*radius;
+        //one turn further out is one word spacing further out
+        let growth = spacing/TAU;
+        let mut spots = vec![(0.0, 0.0)];
+        let mut theta = TAU;
+        while spots.len() < len {
+            let dist = growth*theta;
+            spots.push((theta, dist));
+            theta += spacing/dist;
+        }
+        spots
+    })
+}
+
+fn concentric_layout(len:usize, room:f64) -> Vec<(f64,f64,f64,f64)> {
+    shrink_to_fit(len, room, |radius| {
+        let spacing = 2.0*WORD_GAP*radius;
+        let mut spots = Vec::with_capacity(len);
+        let mut ring = 1;
+        while spots.len() < len {
+            let dist = spacing*(ring as f64 - 0.5);
+            let capacity = ((TAU*dist/spacing).floor() as usize).max(1);
+            let count = capacity.min(len - spots.len());
+            //alternate rings are turned half a step so words don't line up
+            let offset = if ring % 2 == 0 {PI/count as f64} else {0.0};
+            spots.extend((0..count).map(|num| (offset + num as f64*TAU/count as f64, dist)));
+            ring += 1;
+        }
+        spots
+    })
+}
+
+fn weighted_layout(word_list:&[ProcessedWord], room:f64) -> Vec<(f64,f64,f64,f64)> {
+    let ang = basic_angle(word_list, 6, 10, 14);
+    let shares:Vec<f64> = word_list.iter()
+        .map(|word| f64::from(word_weight(word, 6, 10, 14))*ang)
+        .collect();
+    //word radius follows the chord its share of the ring leaves free
+    let fill = 0.9;
+    let widest = shares.iter().fold(0.0, |max:f64, share| max.max((share/2.0).min(PI/2.0).sin()));
+    let dist = room/(1.0 + fill*widest);
+    let mut start = 0.0;
+    shares.iter().map(|share| {
+        let radius = (fill*dist*(share/2.0).min(PI/2.0).sin()).min(MAX_WORD_RADIUS);
+        let word_ang = start + share/2.0;
+        start += share;
+        (radius, word_thick(radius), word_ang, dist)
+    }).collect()
+}
+
+fn central_layout(len:usize, room:f64) -> Vec<(f64,f64,f64,f64)> {
+    let ring_len = len - 1;
+    let chord = if ring_len == 1 {1.0} else {(PI/ring_len as f64).sin()};
+    let fill = 0.9;
+    let radius = (fill*room*chord/(1.0 + fill*chord)).min(0.3*room);
+    let dist = room - radius;
+    let centre_radius = (0.35*room).min(dist - WORD_GAP*radius);
+    let mut layouts = vec![(centre_radius, word_thick(centre_radius), 0.0, 0.0)];
+    layouts.extend((0..ring_len).map(|num| {
+        (radius, word_thick(radius), num as f64*TAU/ring_len as f64, dist)
+    }));
+    layouts
+}
+
 pub fn default_layouts(phrase_length:usize, num:usize) -> (f64,f64,f64,f64) {
     match phrase_length {
         //word_radius, word_thick, word_angle, word_dist
@@ -56,23 +223,86 @@ pub fn default_layouts(phrase_length:usize, num:usize) -> (f64,f64,f64,f64) {
         2 => (350.0,35.0, num as f64 * PI,450.0),
         3 => (350.0,35.0, num as f64 * TAU/3.0,450.0),
         4 => (325.0,32.5, num as f64 * PI/2.0,525.0),
-        len => (
-            300.0 - 8.5*len as f64,
-            20.0 - 0.1*len as f64,
-            num as f64*TAU/(len as f64),
-            500.0 + 20.0*len as f64,
-        ),//Circular pattern
-        //TODO: spiral pattern?
+        len => {
+            //stops shrinking at 30 words, where the size would soon go negative.
+            //The sentence relaxes the sizes from there.
+            let radius = (300.0 - 8.5*len as f64).max(MIN_RING_RADIUS);
+            (
+                radius,
+                (20.0 - 0.1*len as f64).max(word_thick(radius)),
+                num as f64*TAU/(len as f64),
+                500.0 + 20.0*len as f64,
+            )
+        },//Circular pattern, see `word_layouts` for the others
     }
 }
 
-pub fn string_parse(raw_word:String) -> ProcessedWord {
-    let mut word = raw_word.to_lowercase();
-    word = replace_two_char(word);
-    word = replace_repeat_3_char(word);
-    word = replace_repeat_char(word);
-    
-    let (length, vowels, a_flag, z_stem, s_stem, neg_digit) = letter_count(&word);
+//Splits a phrase into words. Tokens made only of punctuation are
+//attached to the previous word (or the next one, at the start).
+pub fn phrase_parse(phrase:&str, config:&Config) -> Result<Vec<ProcessedWord>, Error> {
+    let orth = Orthography::from_config(config)?;
+    let sounds = Orthography::load(gall_phon::SOUNDS)?;
+    let phon = Pronouncer::from_config(config)?;
+    let mut word_list:Vec<ProcessedWord> = Vec::new();
+    let mut pending = Vec::new();
+    for raw_word in phrase.split_whitespace() {
+        let mut processed = string_parse(raw_word.to_string(), &orth, &sounds, phon.as_ref());
+        let drawable = processed.tokens.iter().any(|token| stem_lookup(token).0 != LetterMark::GallMark);
+        if !drawable {
+            match word_list.last_mut() {
+                Some(prev) => {
+                    prev.punct_after.append(&mut processed.punct_before);
+                    prev.punct_after.append(&mut processed.punct_after);
+                },
+                None => {
+                    pending.append(&mut processed.punct_before);
+                    pending.append(&mut processed.punct_after);
+                },
+            }
+            continue;
+        }
+        if !pending.is_empty() {
+            pending.append(&mut processed.punct_before);
+            processed.punct_before = std::mem::take(&mut pending);
+        }
+        word_list.push(processed);
+    }
+    Ok(word_list)
+}
+
+//Leading punctuation is kept as punct_before, punctuation between letters
+//as punct_inside and trailing punctuation as punct_after.
+//Words spelt by sound (phonetic mode or IPA) are read with `sounds` instead of `orth`.
+//They take only their punctuation from the raw word, all of it after the word as the
+//spoken letters don't line up with the written ones.
+pub fn string_parse(raw_word:String, orth:&Orthography, sounds:&Orthography, phon:Option<&Pronouncer>) -> ProcessedWord {
+    let mut tokens = Vec::new();
+    let mut punct_before = Vec::new();
+    let mut punct_inside = Vec::new();
+    let mut punct_after = Vec::new();
+    let spoken = gall_phon::respell(&raw_word, phon).map(|spelling| gall_lex::lex(&spelling, sounds));
+    let mut letters = 0;
+    for token in gall_lex::lex(&raw_word, orth) {
+        match token {
+            Token::Punct(punct) if letters == 0 => punct_before.push(punct),
+            Token::Punct(punct) => punct_after.push(punct),
+            _ => {
+                //a letter after them puts the marks so far inside the word
+                punct_inside.extend(punct_after.drain(..).map(|punct| (letters, punct)));
+                letters += 1;
+                if spoken.is_none() {
+                    tokens.push(token);
+                }
+            },
+        }
+    }
+    let is_spoken = spoken.is_some();
+    if let Some(spoken) = spoken {
+        tokens = spoken;
+        punct_after.splice(0..0, punct_inside.drain(..).map(|(_, punct)| punct));
+    }
+    let word = raw_word.chars().filter(|cha| PunctType::lookup(cha).is_none() && *cha != gall_lex::SEPARATOR).collect();
+    let (length, vowels, a_flag, z_stem, s_stem) = letter_count(&tokens);
     let size = match length {
         0..=4 => {Size::Small},
         5..=9 => {Size::Med},
@@ -80,231 +310,60 @@ pub fn string_parse(raw_word:String) -> ProcessedWord {
     };
     ProcessedWord{
         word,
+        tokens,
         length,
         vowels,
         size,
         a_flag,
         z_stem,
         s_stem,
-        neg_digit,
+        punct_before,
+        punct_inside,
+        punct_after,
+        spoken: is_spoken,
+        stack: true,
     }
 }
 
-fn letter_count(word:&String) -> (usize, usize, bool, bool, bool, Vec<bool>) {
+fn letter_count(tokens:&[Token]) -> (usize, usize, bool, bool, bool) {
     let mut vow_count = 0;
-    let mut count = 0;
     let mut a_flag = false;
     let mut z_stem = false;
     let mut s_stem = false;
-    let mut num_vec = Vec::new();
-    let mut negative_digit = None;
-    let mut negative_flag = false;
-    for letter in word.chars() {
-        count += 1;
-        match letter {
-            'E'|'e'|'I'|'i'|'O'|'o'|'U'|'u' => vow_count += 1,
-            '\u{ea05}'|'\u{ea09}'|'\u{ea0f}'|'\u{ea15}' => vow_count += 2,
-            '\u{ea25}'|'\u{ea29}'|'\u{ea2f}'|'\u{ea35}' => vow_count += 3,
-            'A'|'a' => {vow_count += 1; a_flag = true},
-            '\u{ea01}' => {vow_count += 2; a_flag = true},
-            '\u{ea21}' => {vow_count += 3; a_flag = true},
-            'R'|'r'|'S'|'s'|'T'|'t'|'W'|'w'|'V'|'v' => s_stem = true,
-            '\u{ea12}'..='\u{ea17}'|'\u{ea32}'..='\u{ea37}' => s_stem = true, //repeats
-            '\u{e400}'|'\u{e500}'|'\u{e600}' => s_stem = true, //WH, SH, NT
-            'Q'|'q'|'X'|'x'|'Y'|'y'|'Z'|'z' => z_stem = true,
-            '\u{ea11}'|'\u{ea31}'|'\u{ea18}'..='\u{ea1a}'|'\u{ea38}'..='\u{ea3a}' => z_stem = true, //repeats
-            '\u{e000}'|'\u{e700}'|'\u{e800}'|'\u{e900}' => z_stem = true, //TH, GH,NG, QU
-            _ => {},
-        }
-        match letter {
-            '0'|'1'|'2'|'3'|'4'|'5'|'6'|'7'|'8'|'9' => {
-                if negative_flag {
-                    negative_digit = Some(true);
-                } else {
-                    negative_digit = Some(false);
-                }
-                count -= 1; //the whole number is 1 character
+    for token in tokens {
+        let (l_mark, repeat) = stem_lookup(token);
+        match l_mark {
+            LetterMark::GallVowel(vow) => {
+                vow_count += 1 + usize::from(repeat);
+                a_flag |= vow == VowelType::A;
             },
-            '-' => negative_flag = true,
-            _ => {
-                negative_flag = false;
-                if let Some(neg) = negative_digit {
-                    if !neg {
-                        count += 1;
-                    }
-                    num_vec.push(neg);
-                    negative_digit = None;
-                }
-            }
-        }
-        if let Some(neg) = negative_digit {
-            if !neg {
-                count += 1;
-            }
-            num_vec.push(neg);
+            LetterMark::Stem(StemType::S) => s_stem = true,
+            LetterMark::Stem(StemType::Z) => z_stem = true,
+            _ => {},
         }
     }
-    num_vec.reverse(); //reversed so that it gets popped in the right order
-    (count, vow_count, a_flag, z_stem, s_stem, num_vec)
-}
-
-fn replace_repeat_3_char(lowercase_str:String) -> String {
-    //TODO: Add precursor/middle vowels
-    lowercase_str
-        .replace("aaa", &'\u{ea21}'.to_string())
-        .replace("bbb", &'\u{ea22}'.to_string())
-        .replace("ccc", &'\u{ea23}'.to_string())
-        .replace("ddd", &'\u{ea24}'.to_string())
-        .replace("eee", &'\u{ea25}'.to_string())
-        .replace("fff", &'\u{ea26}'.to_string())
-        .replace("ggg", &'\u{ea27}'.to_string())
-        .replace("hhh", &'\u{ea28}'.to_string())
-        .replace("iii", &'\u{ea29}'.to_string())
-        .replace("jjj", &'\u{ea2a}'.to_string())
-        .replace("kkk", &'\u{ea2b}'.to_string())
-        .replace("lll", &'\u{ea2c}'.to_string())
-        .replace("mmm", &'\u{ea2d}'.to_string())
-        .replace("nnn", &'\u{ea2e}'.to_string())
-        .replace("ooo", &'\u{ea2f}'.to_string())
-        .replace("ppp", &'\u{ea30}'.to_string())
-        .replace("qqq", &'\u{ea31}'.to_string())
-        .replace("rrr", &'\u{ea32}'.to_string())
-        .replace("sss", &'\u{ea33}'.to_string())
-        .replace("ttt", &'\u{ea34}'.to_string())
-        .replace("uuu", &'\u{ea35}'.to_string())
-        .replace("vvv", &'\u{ea36}'.to_string())
-        .replace("www", &'\u{ea37}'.to_string())
-        .replace("xxx", &'\u{ea38}'.to_string())
-        .replace("yyy", &'\u{ea39}'.to_string())
-        .replace("zzz", &'\u{ea3a}'.to_string())
-}
-
-fn replace_repeat_char(lowercase_str:String) -> String {
-    lowercase_str
-        .replace("aa", &'\u{ea01}'.to_string())
-        .replace("bb", &'\u{ea02}'.to_string())
-        .replace("cc", &'\u{ea03}'.to_string())
-        .replace("dd", &'\u{ea04}'.to_string())
-        .replace("ee", &'\u{ea05}'.to_string())
-        .replace("ff", &'\u{ea06}'.to_string())
-        .replace("gg", &'\u{ea07}'.to_string())
-        .replace("hh", &'\u{ea08}'.to_string())
-        .replace("ii", &'\u{ea09}'.to_string())
-        .replace("jj", &'\u{ea0a}'.to_string())
-        .replace("kk", &'\u{ea0b}'.to_string())
-        .replace("ll", &'\u{ea0c}'.to_string())
-        .replace("mm", &'\u{ea0d}'.to_string())
-        .replace("nn", &'\u{ea0e}'.to_string())
-        .replace("oo", &'\u{ea0f}'.to_string())
-        .replace("pp", &'\u{ea10}'.to_string())
-        .replace("qq", &'\u{ea11}'.to_string())
-        .replace("rr", &'\u{ea12}'.to_string())
-        .replace("ss", &'\u{ea13}'.to_string())
-        .replace("tt", &'\u{ea14}'.to_string())
-        .replace("uu", &'\u{ea15}'.to_string())
-        .replace("vv", &'\u{ea16}'.to_string())
-        .replace("ww", &'\u{ea17}'.to_string())
-        .replace("xx", &'\u{ea18}'.to_string())
-        .replace("yy", &'\u{ea19}'.to_string())
-        .replace("zz", &'\u{ea1a}'.to_string())
-}
-
-fn replace_two_char(lowercase_str:String) -> String {
-    lowercase_str
-        .replace("ch", &'\u{e100}'.to_string())
-        .replace("nd", &'\u{e200}'.to_string())
-        .replace("ph", &'\u{e300}'.to_string())
-        .replace("wh", &'\u{e400}'.to_string())
-        .replace("sh", &'\u{e500}'.to_string())
-        .replace("nt", &'\u{e600}'.to_string())
-        .replace("gh", &'\u{e700}'.to_string())
-        .replace("ng", &'\u{e800}'.to_string())
-        .replace("qu", &'\u{e900}'.to_string())
-        .replace("th", &'\u{e000}'.to_string())
+    (tokens.len(), vow_count, a_flag, z_stem, s_stem)
 }
 
-pub fn stem_lookup(letter:&char) -> (LetterMark, u8) {
-    let stem:LetterMark = match letter {
-        'A'|'a'|'\u{ea01}'|'\u{ea21}'                           => LetterMark::GallVowel(VowelType::A),
-        'E'|'e'|'\u{ea05}'|'\u{ea25}'                           => LetterMark::GallVowel(VowelType::E),
-        'I'|'i'|'\u{ea09}'|'\u{ea29}'                           => LetterMark::GallVowel(VowelType::I),
-        'O'|'o'|'\u{ea0f}'|'\u{ea2f}'                           => LetterMark::GallVowel(VowelType::O2),
-        'U'|'u'|'\u{ea15}'|'\u{ea35}'                           => LetterMark::GallVowel(VowelType::U),
-        '█'|'B'|'D'|'F'|'G'|'H'|'b'|'d'|'f'|'g'|'h'             => LetterMark::Stem(StemType::B),
-        'C'|'J'|'K'|'L'|'M'|'N'|'P'|'c'|'j'|'k'|'l'|'m'|'n'|'p' => LetterMark::Stem(StemType::J),
-        'R'|'S'|'T'|'V'|'W'|'r'|'s'|'t'|'v'|'w'                 => LetterMark::Stem(StemType::S),
-        'Q'|'X'|'Y'|'Z'|'q'|'x'|'y'|'z'                         => LetterMark::Stem(StemType::Z), 
-        '0'                                                     => LetterMark::Digit(0),
-        '1'                                                     => LetterMark::Digit(1),
-        '2'                                                     => LetterMark::Digit(2),
-        '3'                                                     => LetterMark::Digit(3),
-        '4'                                                     => LetterMark::Digit(4),
-        '5'                                                     => LetterMark::Digit(5),
-        '6'                                                     => LetterMark::Digit(6),
-        '7'                                                     => LetterMark::Digit(7),
-        '8'                                                     => LetterMark::Digit(8),
-        '9'                                                     => LetterMark::Digit(9),
-        '\u{e100}'..='\u{e2ff}'                                 => LetterMark::Stem(StemType::B), // CH & ND,
-        '\u{e300}'..='\u{e3ff}'                                 => LetterMark::Stem(StemType::J), // PH,
-        '\u{e400}'..='\u{e6ff}'                                 => LetterMark::Stem(StemType::S), // WH, SH, NT
-        '\u{e700}'..='\u{e9ff}'|'\u{e000}'..='\u{e0ff}'         => LetterMark::Stem(StemType::Z), // GH, NG, QU, TH
-        '\u{ea02}'|'\u{ea04}'|'\u{ea06}'|'\u{ea07}'|'\u{ea08}'  => LetterMark::Stem(StemType::B), // repeat BStems
-        '\u{ea03}'|'\u{ea0a}'..='\u{ea0e}'|'\u{ea10}'           => LetterMark::Stem(StemType::J), // repeat JStems
-        '\u{ea12}'|'\u{ea13}'|'\u{ea14}'|'\u{ea16}'|'\u{ea17}'  => LetterMark::Stem(StemType::S), // repeat TStems
-        '\u{ea11}'|'\u{ea18}'|'\u{ea19}'|'\u{ea1a}'             => LetterMark::Stem(StemType::Z), // repeat ZStems
-        '\u{ea22}'|'\u{ea24}'|'\u{ea26}'|'\u{ea27}'|'\u{ea28}'  => LetterMark::Stem(StemType::B), // triple repeat BStems
-        '\u{ea23}'|'\u{ea2a}'..='\u{ea2e}'|'\u{ea30}'           => LetterMark::Stem(StemType::J), // triple repeat JStems
-        '\u{ea32}'|'\u{ea33}'|'\u{ea34}'|'\u{ea36}'|'\u{ea37}'  => LetterMark::Stem(StemType::S), // triple repeat TStems
-        '\u{ea31}'|'\u{ea38}'|'\u{ea39}'|'\u{ea3a}'             => LetterMark::Stem(StemType::Z), // triple repeat ZStems
-        _ => LetterMark::GallMark //TODO
-    };
-    let repeat = match letter {
-        '\u{ea01}'..='\u{ea1a}' => 1,
-        '\u{ea21}'..='\u{ea3a}' => 2,
-        _ => 0,
-    };
-    (stem,repeat)
+pub fn stem_lookup(token:&Token) -> (LetterMark, u8) {
+    match token {
+        Token::Letter{class, repeat, ..} => match (class.stem, class.vowel) {
+            (Some(stem), _) => (LetterMark::Stem(stem), *repeat),
+            (None, Some(vowel)) => (LetterMark::GallVowel(vowel), *repeat),
+            (None, None) => (LetterMark::GallMark, 0),
+        },
+        Token::Number(number) => (LetterMark::Number(number.clone()), 0),
+        Token::Punct(_) => (LetterMark::GallMark, 0),
+    }
 }
 
-pub fn dot_lookup(letter:&char) -> (Option<Decor>,i8) {
-    let dot = match letter {
-        'C'|'D'|'K'|'L'|'Q'|'R'|'Y'|'Z'|'c'|'d'|'k'|'l'|'q'|'r'|'y'|'z' => Some(Decor::Dot),
-        'F'|'G'|'H'|'I'|'M'|'N'|'P'|'S'|'U'|'V'|'W'|'X'|'f'|'g'|'h'|'i'|'m'|'n'|'p'|'s'|'u'|'v'|'w'|'x' => Some(Decor::Dash),
-        'A'|'B'|'E'|'J'|'O'|'T'|'a'|'b'|'e'|'j'|'o'|'t' => None,
-        '0'|'1'|'2'|'3'|'4'|'5'|'6'|'7'|'8'|'9' => None, //TODO
-        '\u{e000}'..='\u{e0ff}' => None, // TH & variants
-        '\u{e800}'..='\u{e9ff}' => Some(Decor::Dash), // QU, NG & variants
-        '\u{e100}'..='\u{e7ff}' => Some(Decor::Dot), // Other two letter variants
-        '\u{ea03}'|'\u{ea04}'|'\u{ea0b}'|'\u{ea0c}'|'\u{ea11}'|'\u{ea12}'|'\u{ea19}'|'\u{ea1a}'=> Some(Decor::Dot), //repeat dots
-        '\u{ea06}'..='\u{ea09}'|'\u{ea0d}'|'\u{ea0e}'|'\u{ea10}'|'\u{ea13}'|'\u{ea15}'..='\u{ea18}'=> Some(Decor::Dash), //repeat dashes
-        '\u{ea01}'|'\u{ea02}'|'\u{ea05}'|'\u{ea0f}'|'\u{ea14}' => None, //repeat nones
-        '\u{ea23}'|'\u{ea24}'|'\u{ea2b}'|'\u{ea2c}'|'\u{ea31}'|'\u{ea32}'|'\u{ea39}'|'\u{ea3a}'=> Some(Decor::Dot), //repeat dots
-        '\u{ea26}'..='\u{ea29}'|'\u{ea2d}'|'\u{ea2e}'|'\u{ea30}'|'\u{ea33}'|'\u{ea35}'..='\u{ea38}'=> Some(Decor::Dash), //repeat dashes
-        '\u{ea21}'|'\u{ea22}'|'\u{ea25}'|'\u{ea2f}'|'\u{ea34}' => None, //repeat nones
-        _ => None
-    };
-    let mut decor_num = 0;
-    if dot.is_some()  {
-        decor_num = match letter {
-            'G'|'I'|'N'|'U'|'V'|'g'|'i'|'n'|'u'|'v'  => 1,
-            'H'|'K'|'P'|'W'|'X'|'Y'|'h'|'k'|'p'|'w'|'x'|'y' => 2,
-            'D'|'F'|'L'|'M'|'R'|'S'|'Z'|'d'|'f'|'l'|'m'|'r'|'s'|'z' => 3,
-            'C'|'Q'|'c'|'q' => 4,
-            '\u{e300}'|'\u{e400}'|'\u{e700}'|'\u{e900}'=> 1, //PH, WH, GH, QU
-            '\u{e100}'|'\u{e500}'=> 2, //CH, SH
-            '\u{e800}' => 3, //NG, 
-            '\u{e200}'|'\u{e600}'|'\u{ea03}'|'\u{ea11}'=> 4, //ND, NT, CC, QQ
-            '\u{ea07}'|'\u{ea09}'|'\u{ea0e}'|'\u{ea15}'|'\u{ea16}' => 1, //GG, II, NN, UU, VV
-            '\u{ea08}'|'\u{ea0b}'|'\u{ea10}'|'\u{ea17}'|'\u{ea18}'|'\u{ea19}' => 2, //HH, KK, PP, WW, XX, YY,
-            '\u{ea04}'|'\u{ea06}'|'\u{ea0c}'|'\u{ea0d}'|'\u{ea12}'|'\u{ea13}'|'\u{ea1a}' => 3, //DD, FF, LL, MM, RR, SS, ZZ
-            '\u{ea27}'|'\u{ea29}'|'\u{ea2e}'|'\u{ea35}'|'\u{ea36}' => 1, //GGG, III, NNN, UUU, VVV
-            '\u{ea28}'|'\u{ea2b}'|'\u{ea30}'|'\u{ea37}'|'\u{ea38}'|'\u{ea39}' => 2, //HHH, KKK, PPP, WWW, XXX, YYY,
-            '\u{ea24}'|'\u{ea26}'|'\u{ea2c}'|'\u{ea2d}'|'\u{ea32}'|'\u{ea33}'|'\u{ea3a}' => 3, //DDD, FFF, LLL, MMM, RRR, SSS, ZZZ
-            '\u{ea23}'|'\u{ea31}'=> 4, // CCC, QQQ
-            _ => 0
-        }
-    };
-    (dot, decor_num)
+pub fn dot_lookup(token:&Token) -> (Option<Decor>,i8) {
+    match token {
+        Token::Letter{class, ..} if class.dots > 0 => (Some(Decor::Dot), class.dots),
+        Token::Letter{class, ..} if class.dashes > 0 => (Some(Decor::Dash), class.dashes),
+        //numbers draw their own marks
+        _ => (None, 0),
+    }
 }
 
 pub fn thi(letter_distance:f64, letter_radius:f64,big_radius:f64) -> Result<f64, Error> {
@@ -0,0 +1,243 @@
+use crate::gall_mark::PunctType;
+use crate::gall_num::GallNumber;
+use crate::gall_orth::{self, LetterClass, Orthography};
+
+//`repeat` counts the extra copies, so "ll" is `l` with a repeat of 1.
+//`text` is the grapheme as the orthography names it, e.g. "th" or "k" for a C read as K.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Token {
+    Letter{text:String, class:LetterClass, repeat:u8},
+    Number(GallNumber),
+    Punct(PunctType),
+}
+
+//Longest run of the same letter drawn as one stack, longer runs start a new one
+pub const MAX_REPEAT: u8 = 2;
+//Written between two letters to keep them apart, e.g. "en|gage" or "hot|house"
+pub const SEPARATOR: char = '|';
+
+impl Token {
+    fn same_letter(&self, other:&Token) -> bool {
+        match (self, other) {
+            (Token::Letter{text:a, ..}, Token::Letter{text:b, ..}) => a == b,
+            _ => false,
+        }
+    }
+    fn repeat_mut(&mut self) -> Option<&mut u8> {
+        match self {
+            Token::Letter{repeat, ..} => Some(repeat),
+            _ => None,
+        }
+    }
+}
+
+//Splits one whitespace separated word into tokens, reading left to right.
+//Letters the orthography doesn't know are accent folded ("é" is read as "e"),
+//anything still unknown is dropped. That includes a '-' that isn't a number's sign,
+//so "--5" and "5-" both read as a number and nothing else.
+pub fn lex(raw_word:&str, orth:&Orthography) -> Vec<Token> {
+    let mut chars:Vec<char> = raw_word.chars().flat_map(char::to_lowercase).collect();
+    let mut tokens:Vec<Token> = Vec::new();
+    let mut pos = 0;
+    let mut split = false;
+    while pos < chars.len() {
+        if chars[pos] == SEPARATOR {
+            split = true;
+            pos += 1;
+            continue;
+        }
+        let (found, len) = match next_tokens(&chars, pos, orth) {
+            Some(found) => found,
+            None => {
+                match gall_orth::fold(chars[pos]) {
+                    Some(folded) => _ = chars.splice(pos..=pos, folded.chars()),
+                    None => pos += 1,
+                }
+                continue;
+            },
+        };
+        pos += len;
+        for token in found {
+            //a letter straight after the same letter becomes a repeat
+            if let Some(last) = tokens.last_mut().filter(|_| !split) {
+                if last.same_letter(&token) {
+                    if let Some(repeat) = last.repeat_mut().filter(|repeat| **repeat < MAX_REPEAT) {
+                        *repeat += 1;
+                        continue;
+                    }
+                }
+            }
+            tokens.push(token);
+            split = false;
+        }
+    }
+    tokens
+}
+
+//The tokens starting at `pos` and how many characters they take up.
+//Aliases give several letters, or none at all for silent ones.
+fn next_tokens(chars:&[char], pos:usize, orth:&Orthography) -> Option<(Vec<Token>, usize)> {
+    let cha = chars[pos];
+    let next = chars.get(pos + 1).copied();
+    if cha.is_ascii_digit() || (cha == '-' && next.is_some_and(|next| next.is_ascii_digit())) {
+        return number_token(chars, pos).map(|(token, len)| (vec![token], len))
+    }
+    if let Some(punct) = PunctType::lookup(&cha) {
+        return Some((vec![Token::Punct(punct)], 1))
+    }
+    let (grapheme, len) = orth.grapheme_at(chars, pos)?;
+    let letters = grapheme.letters.iter()
+        .map(|(text, class)| Token::Letter{text:text.clone(), class:*class, repeat:0})
+        .collect();
+    Some((letters, len))
+}
+
+//A sign, digits, and any '.' or ',' with a digit on both sides.
+//A second '.' ends the number, so "1.2.3" is 1.2, a period and 3.
+fn number_token(chars:&[char], pos:usize) -> Option<(Token, usize)> {
+    let mut end = pos + 1;
+    let mut point = false;
+    while let Some(&cha) = chars.get(end) {
+        if cha == '.' && point {
+            break;
+        }
+        let joined = matches!(cha, '.'|',')
+            && chars[end - 1].is_ascii_digit()
+            && chars.get(end + 1).is_some_and(|next| next.is_ascii_digit());
+        if cha.is_ascii_digit() || joined {
+            point |= cha == '.';
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    let run:String = chars[pos..end].iter().collect();
+    GallNumber::parse(&run).map(|number| (Token::Number(number), end - pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english() -> Orthography {
+        Orthography::load("english").unwrap()
+    }
+    //Letters as text, with "*n" for repeats, e.g. "r u n*1 i ng"
+    fn spell(tokens:&[Token]) -> String {
+        let letters:Vec<String> = tokens.iter().filter_map(|token| match token {
+            Token::Letter{text, repeat:0, ..} => Some(text.clone()),
+            Token::Letter{text, repeat, ..} => Some(format!("{}*{}", text, repeat)),
+            _ => None,
+        }).collect();
+        letters.join(" ")
+    }
+    fn lex_all(raw_word:&str) -> String {
+        spell(&lex(raw_word, &english()))
+    }
+
+    #[test]
+    fn repeated_digraph() {
+        assert_eq!(lex_all("thth"), "th*1");
+    }
+
+    #[test]
+    fn double_letter_before_digraph() {
+        assert_eq!(lex_all("nng"), "n ng");
+        assert_eq!(lex_all("running"), "r u n*1 i ng");
+    }
+
+    #[test]
+    fn h_digraph_wins_overlap() {
+        assert_eq!(lex_all("month"), "m o n th");
+        assert_eq!(lex_all("ant"), "a nt");
+    }
+
+    #[test]
+    fn four_letter_repeat() {
+        assert_eq!(lex_all("oooo"), "o*2 o");
+        assert_eq!(lex_all("lll"), "l*2");
+    }
+
+    #[test]
+    fn numbers_and_punctuation() {
+        let tokens = lex("\"-1,024.5x.", &english());
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0], Token::Punct(PunctType::Quote));
+        assert_eq!(tokens[1], Token::Number(GallNumber{
+            negative: true,
+            digits: vec![1, 0, 2, 4, 5],
+            point: Some(4),
+            groups: vec![1],
+        }));
+        assert_eq!(spell(&tokens[2..3]), "x");
+        assert_eq!(tokens[3], Token::Punct(PunctType::Period));
+    }
+
+    #[test]
+    fn second_point_ends_number() {
+        let tokens = lex("1.2.3", &english());
+        let number = |run| Token::Number(GallNumber::parse(run).unwrap());
+        assert_eq!(tokens, vec![number("1.2"), Token::Punct(PunctType::Period), number("3")]);
+    }
+
+    #[test]
+    fn stray_minus_signs() {
+        let number = |run| vec![Token::Number(GallNumber::parse(run).unwrap())];
+        assert_eq!(lex("--5", &english()), number("-5"));
+        assert_eq!(lex("5-", &english()), number("5"));
+    }
+
+    #[test]
+    fn trailing_point_is_punctuation() {
+        let tokens = lex("42.", &english());
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1], Token::Punct(PunctType::Period));
+    }
+
+    #[test]
+    fn case_and_unknown_characters() {
+        assert_eq!(lex_all("Sh-\u{2603}"), "sh");
+    }
+
+    #[test]
+    fn accents_are_folded() {
+        assert_eq!(lex_all("Café"), "c a f e");
+        assert_eq!(lex_all("straße"), "s t r a s*1 e");
+        assert_eq!(lex_all("þing"), "th i ng");
+    }
+
+    #[test]
+    fn separator_splits_digraphs_and_repeats() {
+        assert_eq!(lex_all("en|gage"), "e n g a g e");
+        assert_eq!(lex_all("l|l"), "l l");
+        assert_eq!(lex_all("|th|"), "th");
+    }
+
+    #[test]
+    fn disabled_digraphs() {
+        let mut orth = english();
+        orth.disable(&["ng".to_string(), "th".to_string()]).unwrap();
+        assert_eq!(spell(&lex("ng", &orth)), "n g");
+        //with TH off, NT no longer gives way to it
+        assert_eq!(spell(&lex("nth", &orth)), "nt h");
+        assert!(orth.disable(&["x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn preset_aliases() {
+        let spell_in = |name:&str, raw_word:&str| spell(&lex(raw_word, &Orthography::load(name).unwrap()));
+        assert_eq!(spell_in("sherman-ck", "cycle"), "s y k l e");
+        assert_eq!(spell_in("sherman-ck", "back"), "b a k");
+        assert_eq!(spell_in("spanish", "mañana"), "m a n y a n a");
+        assert_eq!(spell_in("spanish", "hola"), "o l a");
+        assert_eq!(spell_in("german", "schön"), "sh o e n");
+        assert_eq!(spell_in("french", "beauté"), "b o t e");
+    }
+
+    #[test]
+    fn every_preset_loads() {
+        for (name, _) in gall_orth::PRESETS {
+            assert!(Orthography::load(name).is_ok(), "{}", name);
+        }
+    }
+}
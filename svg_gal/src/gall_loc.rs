@@ -1,5 +1,5 @@
 use std::cell::Cell;
-use std::f64::consts::{FRAC_PI_2, PI};
+use std::f64::consts::FRAC_PI_2;
 use std::rc::Rc;
 
 use crate::gall_ang::{self, GallAng};
@@ -117,10 +117,7 @@ impl GallLoc {
 
 impl GallRelLoc {
     pub fn new(angle_ref:Rc<Cell<GallAng>>, ang_offset:f64, letter_radius:Rc<Cell<f64>>, dist_offset:f64, center_ref: Rc<Cell<(f64,f64)>>) -> GallRelLoc {
-        let angle = match angle_ref.get().ang() {
-            Some(ang) =>  Some(ang + ang_offset),
-            None => None,
-        };
+        let angle = angle_ref.get().ang().map(|ang| ang + ang_offset);
         let pos = calc_xy(letter_radius.get(), angle, center_ref.get());
         GallRelLoc {
             angle: angle_ref,
@@ -137,6 +134,10 @@ impl GallRelLoc {
     fn base_ang(&self) -> Option<f64> {
         self.angle.get().ang()
     }
+    //Angle of whatever this location is relative to
+    pub fn ref_ang(&self) -> Option<f64> {
+        self.base_ang()
+    }
     pub fn set_dist(&mut self, dist_ref:Rc<Cell<f64>>) {
         self.letter_radius = dist_ref;
     }
@@ -229,10 +230,9 @@ impl PolarOrdinate for GallLoc {
         self.update_xy();
     }
     fn mut_dist(&mut self, new_dist:f64)-> Result<(), Error> {
-        match self.ord.mut_dist(new_dist) {
-            Ok(_) => Ok(self.update_xy()),
-            Err(e) => Err(e),
-        }
+        self.ord.mut_dist(new_dist)?;
+        self.update_xy();
+        Ok(())
     }
     fn ang(&self) -> Option<f64> {
         self.ord.ang()
@@ -251,22 +251,18 @@ impl PolarOrdinate for GallRelLoc {
             let new_ang = gall_ang::constrain(ang);
             self.ang_offset = new_ang - old_ang;
             self.update_xy();
-        } else {}//Don't panic if base ang is None
-        //self.update_xy();
+        } //Don't panic if base ang is None
     }
     fn mut_dist(&mut self, new_dist: f64) -> Result<(), Error> {
         if new_dist.is_sign_negative() {
             return Err(Error::new(GallError::NegativeDistanceErr))
         }
         self.dist_offset = new_dist - self.letter_radius.get();
-        Ok(self.update_xy())
+        self.update_xy();
+        Ok(())
     }
     fn ang(&self) -> Option<f64> {
-        if let Some(ang) = self.base_ang() {
-            Some(self.ang_offset + ang)
-        } else {
-            None
-        }
+        self.base_ang().map(|ang| self.ang_offset + ang)
     }
     fn dist(&self) -> f64 {
         self.dist_offset + self.letter_radius.get()
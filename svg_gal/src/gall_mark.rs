@@ -0,0 +1,221 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gall_circle::{ChildCircle, Circle, HollowCircle};
+use crate::gall_errors::Error;
+use crate::gall_loc::{GallLoc, LocHolder, Location};
+use crate::gall_ord::{GallOrd, OrdHolder, PolarOrdinate};
+
+//Sentence punctuation, drawn on the sentence circle between words
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PunctType {
+    Period,
+    Question,
+    Exclaim,
+    Comma,
+    Semicolon,
+    Colon,
+    Quote,
+    Apostrophe,
+}
+
+pub struct GallMark {
+    loc: GallLoc,
+    radius: Rc<Cell<f64>>,
+    parent_radius: Rc<Cell<f64>>,
+    parent_thickness: Rc<Cell<f64>>,
+    pub punct: PunctType,
+}
+
+impl PunctType {
+    pub fn lookup(cha:&char) -> Option<PunctType> {
+        match cha {
+            '.' => Some(PunctType::Period),
+            '?' => Some(PunctType::Question),
+            '!' => Some(PunctType::Exclaim),
+            ',' => Some(PunctType::Comma),
+            ';' => Some(PunctType::Semicolon),
+            ':' => Some(PunctType::Colon),
+            '"'|'\u{201c}'|'\u{201d}' => Some(PunctType::Quote),
+            '\''|'\u{2018}'|'\u{2019}' => Some(PunctType::Apostrophe),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            PunctType::Period => "period",
+            PunctType::Question => "question",
+            PunctType::Exclaim => "exclaim",
+            PunctType::Comma => "comma",
+            PunctType::Semicolon => "semicolon",
+            PunctType::Colon => "colon",
+            PunctType::Quote => "quote",
+            PunctType::Apostrophe => "apostrophe",
+        }
+    }
+    pub fn symbol(&self) -> char {
+        match self {
+            PunctType::Period => '.',
+            PunctType::Question => '?',
+            PunctType::Exclaim => '!',
+            PunctType::Comma => ',',
+            PunctType::Semicolon => ';',
+            PunctType::Colon => ':',
+            PunctType::Quote => '"',
+            PunctType::Apostrophe => '\'',
+        }
+    }
+}
+
+//What a mark is drawn with: rings and dots by centre and radius, ticks by their ends
+pub enum MarkPart {
+    Ring((f64,f64), f64),
+    Dot((f64,f64), f64),
+    Tick((f64,f64), (f64,f64)),
+}
+
+impl GallMark {
+    pub fn new<T:HollowCircle>(loc:GallLoc, radius:f64, punct:PunctType, parent:&T) -> GallMark {
+        GallMark {
+            loc,
+            radius: Rc::new(Cell::new(radius)),
+            parent_radius: parent.get_radius(),
+            parent_thickness: parent.get_thickness(),
+            punct,
+        }
+    }
+    pub fn parts(&self) -> Vec<MarkPart> {
+        let rad = self.radius();
+        let ang = self.ang().unwrap_or(0.0);
+        let centre = self.svg_ord();
+        match self.punct {
+            PunctType::Period => vec![MarkPart::Ring(centre, rad)],
+            PunctType::Comma => vec![MarkPart::Dot(centre, rad)],
+            PunctType::Semicolon => vec![MarkPart::Ring(centre, rad), MarkPart::Dot(centre, rad*0.4)],
+            PunctType::Colon => vec![MarkPart::Ring(centre, rad), MarkPart::Ring(centre, rad*0.5)],
+            PunctType::Question|PunctType::Exclaim => {
+                //dots sitting just inside the sentence line
+                let count = if self.punct == PunctType::Question {2} else {3};
+                let dist = self.parent_inner() - rad;
+                let spread = 1.5*rad/dist;
+                let mut tracker = GallLoc::new(ang, dist, self.get_center());
+                (0..count).map(|n| {
+                    let offset = n as f64 - f64::from(count - 1)/2.0;
+                    tracker.mut_ang(ang + spread*offset);
+                    MarkPart::Dot(tracker.svg_ord(), rad*0.5)
+                }).collect()
+            },
+            PunctType::Quote|PunctType::Apostrophe => {
+                //short ticks crossing the sentence line
+                let count = if self.punct == PunctType::Quote {2} else {1};
+                let spread = rad/self.parent_radius();
+                let mut tracker = GallLoc::new(ang, self.parent_radius(), self.get_center());
+                (0..count).map(|n| {
+                    let tick_ang = ang + spread*(n as f64 - f64::from(count - 1)/2.0);
+                    tracker.mut_ang_d(self.parent_radius() - rad, tick_ang);
+                    let start = tracker.svg_ord();
+                    tracker.mut_ang_d(self.parent_radius() + rad, tick_ang);
+                    MarkPart::Tick(start, tracker.svg_ord())
+                }).collect()
+            },
+        }
+    }
+}
+
+impl Circle for GallMark {
+    fn radius(&self) -> f64 {
+        self.radius.get()
+    }
+    fn mut_radius(&mut self, new_radius:f64) -> Result<(),Error> {
+        self.radius.set(new_radius);
+        Ok(())
+    }
+    fn get_radius(&self) -> Rc<Cell<f64>> {
+        self.radius.clone()
+    }
+}
+impl ChildCircle for GallMark {
+    fn parent_radius(&self) -> f64 {
+        self.parent_radius.get()
+    }
+    fn parent_thick(&self) -> f64 {
+        self.parent_thickness.get()
+    }
+    fn get_parent_radius(&self) -> Rc<Cell<f64>> {
+        self.parent_radius.clone()
+    }
+    fn get_parent_thick(&self) -> Rc<Cell<f64>> {
+        self.parent_thickness.clone()
+    }
+}
+impl LocHolder for GallMark {
+    fn loc(&self) -> &GallLoc {
+        &self.loc
+    }
+    fn mut_loc(&mut self) -> &mut GallLoc {
+        &mut self.loc
+    }
+}
+impl OrdHolder for GallMark {
+    fn ord(&self) -> &GallOrd {
+        &self.loc.ord
+    }
+    fn mut_ord(&mut self) -> &mut GallOrd {
+        &mut self.loc.ord
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+    use crate::Config;
+
+    fn kinds(mark:&GallMark) -> Vec<&'static str> {
+        mark.parts().iter().map(|part| match part {
+            MarkPart::Ring(..) => "ring",
+            MarkPart::Dot(..) => "dot",
+            MarkPart::Tick(..) => "tick",
+        }).collect()
+    }
+
+    #[test]
+    fn parts_of_each_mark() {
+        let config = Config::default();
+        let sent = crate::layout("a", &config).unwrap();
+        let expected = [
+            (PunctType::Period, vec!["ring"]),
+            (PunctType::Comma, vec!["dot"]),
+            (PunctType::Semicolon, vec!["ring", "dot"]),
+            (PunctType::Colon, vec!["ring", "ring"]),
+            (PunctType::Question, vec!["dot"; 2]),
+            (PunctType::Exclaim, vec!["dot"; 3]),
+            (PunctType::Quote, vec!["tick"; 2]),
+            (PunctType::Apostrophe, vec!["tick"]),
+        ];
+        for (punct, parts) in expected {
+            let loc = GallLoc::new(1.0, sent.radius(), sent.pos_ref());
+            let mark = GallMark::new(loc, config.punct_radius, punct, &sent);
+            assert_eq!(kinds(&mark), parts, "{}", punct.name());
+        }
+    }
+
+    #[test]
+    fn marks_sit_between_words() {
+        let config = Config::default();
+        let sent = crate::layout("hello, world!", &config).unwrap();
+        let puncts:Vec<PunctType> = sent.marks.iter().map(|mark| mark.punct).collect();
+        assert_eq!(puncts, [PunctType::Comma, PunctType::Exclaim]);
+        let (hello, world) = (sent.words[0].ang().unwrap(), sent.words[1].ang().unwrap());
+        //going round from the first word angle to the second
+        let between = |ang:f64, start:f64, end:f64| (ang - start).rem_euclid(TAU) < (end - start).rem_euclid(TAU);
+        let (comma, exclaim) = (sent.marks[0].ang().unwrap(), sent.marks[1].ang().unwrap());
+        assert!(between(comma, hello, world), "comma at {} is not after hello at {}", comma, hello);
+        //the last word's gap runs round to the first word
+        assert!(between(exclaim, world, hello), "! at {} is not after world at {}", exclaim, world);
+    }
+}
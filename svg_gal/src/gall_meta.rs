@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use svg::node::element::Element;
+use svg::node::{Node, Text};
+
+use crate::gall_config::Config;
+use crate::gall_errors::{Error, GallError};
+use crate::gall_fn::{LetterMark, ProcessedWord};
+use crate::gall_lex::{self, Token, SEPARATOR};
+use crate::gall_mark::PunctType;
+use crate::gall_num::GallNumber;
+use crate::gall_orth::{self, LetterClass, Orthography, Table};
+use crate::gall_phon;
+use crate::gall_stem::StemType;
+use crate::gall_vowel::VowelType;
+
+//Id of the <metadata> element holding the JSON below
+pub const METADATA_ID: &str = "gallifreyan";
+const VERSION: u32 = 1;
+
+//What a render says, written into the SVG so it can be read back.
+//A preset orthography is recorded by name, a table file by its name and letters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SentenceMeta {
+    pub version: u32,
+    pub orthography: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<Table>,
+    pub words: Vec<WordMeta>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WordMeta {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<PunctType>,
+    pub letters: Vec<LetterMeta>,
+    //marks between letters, after the given number of letters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inside: Vec<(usize, PunctType)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<PunctType>,
+    //spelt by sound, with the letters of gall_phon::SOUNDS
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub spoken: bool,
+}
+
+//One stack of letters: a stem or vowel with its dots or dashes, or a number
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LetterMeta {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stem: Option<StemType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vowel: Option<VowelType>,
+    #[serde(default)]
+    pub dots: i8,
+    #[serde(default)]
+    pub dashes: i8,
+    #[serde(default)]
+    pub repeat: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<GallNumber>,
+}
+
+impl SentenceMeta {
+    pub fn new(word_list:&[ProcessedWord], config:&Config) -> Result<SentenceMeta, Error> {
+        let words = word_list.iter().enumerate().map(|(w, word)| {
+            let letters = word.tokens.iter().filter_map(|token| match token {
+                Token::Letter{class, repeat, ..} => Some((*class, *repeat, None)),
+                Token::Number(number) => Some((LetterClass::default(), 0, Some(number.clone()))),
+                Token::Punct(_) => None,
+            }).enumerate().map(|(l, (class, repeat, number))| LetterMeta {
+                id: letter_id(&word_id(w), l),
+                stem: class.stem,
+                vowel: class.vowel,
+                dots: class.dots,
+                dashes: class.dashes,
+                repeat,
+                number,
+            }).collect();
+            WordMeta {
+                id: word_id(w),
+                before: word.punct_before.clone(),
+                letters,
+                inside: word.punct_inside.clone(),
+                after: word.punct_after.clone(),
+                spoken: word.spoken,
+            }
+        }).collect();
+        let (orthography, table) = if gall_orth::is_preset(&config.orthography) {
+            (config.orthography.clone(), None)
+        } else {
+            //the file name alone, the folder it sits in means nothing elsewhere
+            let name = Path::new(&config.orthography).file_stem()
+                .map_or_else(|| config.orthography.clone(), |stem| stem.to_string_lossy().into_owned());
+            (name, Some(Table::load(&config.orthography)?))
+        };
+        Ok(SentenceMeta {
+            version: VERSION,
+            orthography,
+            table,
+            words,
+        })
+    }
+    pub fn to_element(&self) -> Element {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        let mut element = Element::new("metadata");
+        element.assign("id", METADATA_ID);
+        element.append(Text::new(json));
+        element
+    }
+    //Finds our <metadata> element in an SVG file written by `render`
+    pub fn from_svg(svg_text:&str) -> Result<SentenceMeta, Error> {
+        let open = format!("id=\"{}\"", METADATA_ID);
+        let start = svg_text.find(&open)
+            .and_then(|at| svg_text[at..].find('>').map(|end| at + end + 1))
+            .ok_or_else(|| decode_failed("no gallifreyan metadata, was this file made by svg_gal?"))?;
+        let end = svg_text[start..].find("</metadata>")
+            .ok_or_else(|| decode_failed("metadata is not closed"))?;
+        let json = svg_text[start..start + end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&");
+        let meta:SentenceMeta = serde_json::from_str(&json).map_err(|e| decode_failed(&e.to_string()))?;
+        if meta.version > VERSION {
+            return Err(decode_failed(&format!("metadata version {} is newer than {}", meta.version, VERSION)))
+        }
+        Ok(meta)
+    }
+    //The phrase spelt with `orth`, or `sounds` for words spelt by sound,
+    //words separated by spaces
+    pub fn phrase(&self, orth:&Orthography, sounds:&Orthography) -> Result<String, Error> {
+        let words:Result<Vec<String>, Error> = self.words.iter()
+            .map(|word| word.text(if word.spoken {sounds} else {orth}))
+            .collect();
+        Ok(words?.join(" "))
+    }
+}
+
+impl WordMeta {
+    //Letters are spelt with the shortest grapheme drawn the same way. A separator
+    //goes in wherever the letters would otherwise be read back differently.
+    fn text(&self, orth:&Orthography) -> Result<String, Error> {
+        let mut expected = Vec::new();
+        let mut text = String::new();
+        for (num, letter) in self.letters.iter().enumerate() {
+            for &(_, punct) in self.inside.iter().filter(|(at, _)| *at == num) {
+                expected.push(Token::Punct(punct));
+                text.push(punct.symbol());
+            }
+            let (token, piece) = match &letter.number {
+                Some(number) => (Token::Number(number.clone()), number.to_string()),
+                None => {
+                    let class = LetterClass {
+                        stem: letter.stem,
+                        vowel: letter.vowel,
+                        dots: letter.dots,
+                        dashes: letter.dashes,
+                    };
+                    let key = orth.spelling(&class)
+                        .ok_or_else(|| decode_failed(&format!("{} has no letter in the orthography", letter.id)))?;
+                    let token = Token::Letter{text:key.to_string(), class, repeat:letter.repeat};
+                    (token, key.repeat(1 + usize::from(letter.repeat)))
+                },
+            };
+            expected.push(token);
+            let joined = format!("{}{}", text, piece);
+            text = if gall_lex::lex(&joined, orth) == expected {
+                joined
+            } else {
+                format!("{}{}{}", text, SEPARATOR, piece)
+            };
+        }
+        let before:String = self.before.iter().map(PunctType::symbol).collect();
+        let after:String = self.after.iter().map(PunctType::symbol).collect();
+        Ok(format!("{}{}{}", before, text, after))
+    }
+}
+
+//Reads the phrase back out of an SVG written by `render`
+pub fn decode(svg_text:&str) -> Result<String, Error> {
+    let meta = SentenceMeta::from_svg(svg_text)?;
+    let orth = match &meta.table {
+        Some(table) => Orthography::from_table(&meta.orthography, table)?,
+        None if gall_orth::is_preset(&meta.orthography) => Orthography::load(&meta.orthography)?,
+        None => return Err(decode_failed(&format!("orthography {} is not a preset and its letters are missing", meta.orthography))),
+    };
+    meta.phrase(&orth, &Orthography::load(gall_phon::SOUNDS)?)
+}
+
+pub fn word_id(word:usize) -> String {
+    format!("w{}", word)
+}
+pub fn letter_id(word_id:&str, letter:usize) -> String {
+    format!("{}-l{}", word_id, letter)
+}
+//CSS classes of a letter's <g>, e.g. "letter stem stem-b"
+pub fn letter_class(mark:&LetterMark) -> String {
+    match mark {
+        LetterMark::Stem(stem) => format!("letter stem stem-{}", stem.name()),
+        LetterMark::GallVowel(vowel) => format!("letter vowel vowel-{}", vowel.name()),
+        LetterMark::Number(_) => "letter number".to_string(),
+        LetterMark::GallMark => "letter".to_string(),
+    }
+}
+//The source text of a letter, as given to `data-letter`
+pub fn token_text(token:&Token) -> String {
+    match token {
+        Token::Letter{text, repeat, ..} => text.repeat(1 + usize::from(*repeat)),
+        Token::Number(number) => number.to_string(),
+        Token::Punct(punct) => punct.symbol().to_string(),
+    }
+}
+
+fn decode_failed(message:&str) -> Error {
+    Error::new(GallError::DecodeFailed(message.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(phrase:&str, config:&Config) -> String {
+        let svg_text = crate::render_svg_string(phrase, config, 1).unwrap();
+        decode(&svg_text).unwrap()
+    }
+
+    #[test]
+    fn decodes_own_render() {
+        let config = Config::default();
+        assert_eq!(round_trip("\"Hello, world!\" en|gage", &config), "\"hello, world!\" en|gage");
+        assert_eq!(round_trip("r2d2 -3.14 1|2 oooo", &config), "r2d2 -3.14 1|2 oooo");
+        //written the shortest way that draws the same
+        assert_eq!(round_trip("Café don't", &config), "cafe don't");
+    }
+
+    #[test]
+    fn decodes_with_recorded_orthography() {
+        let config = Config {
+            orthography: "german".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(round_trip("Straße", &config), "strasse");
+    }
+
+    #[test]
+    fn spoken_words_keep_their_sounds() {
+        //spanish has no th or sh, the sounds are drawn with the english letters
+        let config = Config {
+            orthography: "spanish".to_string(),
+            phonetic: true,
+            ..Config::default()
+        };
+        assert_eq!(round_trip("think shush", &config), "thingk shush");
+    }
+
+    #[test]
+    fn decodes_with_embedded_table() {
+        let path = std::env::temp_dir().join("svg_gal_embedded_table.toml");
+        std::fs::write(&path, "base = \"english\"\nremove = [\"th\"]\n").unwrap();
+        let config = Config {
+            orthography: path.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        let svg_text = crate::render_svg_string("think", &config, 1).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!svg_text.contains(&*path.to_string_lossy()));
+        //english would need "t|hink" to keep the t and h apart
+        assert_eq!(decode(&svg_text).unwrap(), "think");
+    }
+
+    #[test]
+    fn rejects_foreign_svg() {
+        assert!(decode("<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_err());
+    }
+}
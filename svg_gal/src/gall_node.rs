@@ -7,10 +7,27 @@ use crate::{gall_ang, gall_fn};
 use crate::gall_loc::{GallRelLoc, Location};
 use crate::gall_ord::PolarOrdinate;
 
+//Consonant dashes may leave their letter in any direction that clears it.
+//Vowel dashes keep to one side: I towards the word centre, U away from it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NodeKind {
+    Letter,
+    VowelIn,
+    VowelOut,
+}
+
+//How far a vowel dash may turn away from its side
+pub const VOWEL_DASH_SPREAD: f64 = PI/4.0;
+
 pub struct GallNode {
     loc: GallRelLoc,
     l_dist: Rc<Cell<f64>>,
     w_rad: Rc<Cell<f64>>,
+    pub kind: NodeKind,
+    //id of the letter the dash belongs to
+    pub letter: String,
+    //where an unpaired inward vowel dash ends
+    word_center: Option<Rc<Cell<(f64,f64)>>>,
 }
 
 impl GallNode  {
@@ -19,8 +36,40 @@ impl GallNode  {
             loc,
             l_dist,
             w_rad: word_ord,
+            kind: NodeKind::Letter,
+            letter: String::new(),
+            word_center: None,
         }
     }
+    pub fn vowel(loc:GallRelLoc, l_dist:Rc<Cell<f64>>, w_rad:Rc<Cell<f64>>, kind:NodeKind, word_center:Rc<Cell<(f64,f64)>>) -> GallNode {
+        GallNode {
+            loc,
+            l_dist,
+            w_rad,
+            kind,
+            letter: String::new(),
+            word_center: Some(word_center),
+        }
+    }
+    //The direction a vowel dash points in, None for consonant dashes
+    pub fn vowel_dir(&self) -> Option<f64> {
+        let base = self.loc.ref_ang()?;
+        match self.kind {
+            NodeKind::Letter => None,
+            NodeKind::VowelIn => Some(gall_ang::constrain(base + PI)),
+            NodeKind::VowelOut => Some(base),
+        }
+    }
+    //Where a vowel dash leaves its ring, fixed whatever it pairs with
+    pub fn vowel_point(&self) -> Option<(f64,f64)> {
+        let dir = self.vowel_dir()?;
+        let (x, y) = self.get_center().get();
+        let (sin, cos) = dir.sin_cos();
+        Some((x + self.dist()*sin, y + self.dist()*cos))
+    }
+    pub fn word_center(&self) -> Option<Rc<Cell<(f64,f64)>>> {
+        self.word_center.clone()
+    }
     pub fn thi(&self) -> Result<f64, Error> {
         gall_fn::thi(self.l_dist.get(), self.loc.dist(), self.w_rad.get())
     }
@@ -40,6 +89,15 @@ impl GallNode  {
             gall_ang::constrain(ang - theta + PI)) 
         } else {(0.0,TAU)}
     }
+    //A direction the dash can always take: a vowel's own side, or the middle of
+    //the gap a consonant's dash leaves its letter through
+    pub fn default_ang(&self) -> f64 {
+        if let Some(dir) = self.vowel_dir() {
+            return dir
+        }
+        let (cw, ccw) = self.ang_bounds();
+        gall_ang::constrain(cw + (ccw - cw).rem_euclid(TAU)/2.0)
+    }
     pub fn node_test(&self, node2:&GallNode)-> bool {
         //pass on true
         self.center_test(node2) &&
@@ -49,10 +107,19 @@ impl GallNode  {
         self.get_center() != node2.get_center()
     }
     pub fn node_angle_test(&self, node2:&GallNode) -> bool {
+        if let Some((x1, y1)) = self.vowel_point() {
+            //vowel rings often share a centre with their stem, so look from the
+            //side of the ring the dash leaves from instead
+            let (x2, y2) = node2.get_center().get();
+            return self.angle_test(gall_ang::svg_ang2gall_ang((y2-y1).atan2(x2-x1)))
+        }
         let ang = self.cent_ang2cent_ang(node2);
         self.angle_test(ang)
     }
     pub fn angle_test(&self, ang:f64) -> bool {
+        if self.kind != NodeKind::Letter {
+            return self.vowel_dir().is_none_or(|dir| gall_ang::diff(ang, dir) <= VOWEL_DASH_SPREAD)
+        }
         let (cw, ccw) = self.ang_bounds();
         if let Some(gap) = self.broken_gap() {
             if gap {
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gall_loc::{GallRelLoc, Location};
+use crate::gall_ord::PolarOrdinate;
+
+//A run of digits read as one number, e.g. "-1,024.5"
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GallNumber {
+    pub negative: bool,
+    pub digits: Vec<u8>,
+    //number of digits before the decimal point, if there is one
+    pub point: Option<usize>,
+    //digit counts before each group separator
+    pub groups: Vec<usize>,
+}
+
+impl GallNumber {
+    //Expects an optional leading '-', then digits with '.' or ',' between them
+    pub fn parse(run:&str) -> Option<GallNumber> {
+        let mut number = GallNumber::default();
+        let mut chars = run.chars().peekable();
+        if chars.peek() == Some(&'-') {
+            number.negative = true;
+            chars.next();
+        }
+        for cha in chars {
+            match cha {
+                '0'..='9' => number.digits.push(cha as u8 - b'0'),
+                '.' if number.point.is_none() && !number.digits.is_empty() => {
+                    number.point = Some(number.digits.len())
+                },
+                ',' if !number.digits.is_empty() => number.groups.push(number.digits.len()),
+                _ => return None,
+            }
+        }
+        if number.digits.is_empty() || number.point == Some(number.digits.len())
+        || number.groups.last() == Some(&number.digits.len()) {
+            None
+        } else {
+            Some(number)
+        }
+    }
+}
+
+//Written back out the way `parse` reads it
+impl fmt::Display for GallNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        for (n, digit) in self.digits.iter().enumerate() {
+            if self.point == Some(n) {
+                write!(f, ".")?;
+            } else if n > 0 && self.groups.contains(&n) {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", digit)?;
+        }
+        Ok(())
+    }
+}
+
+//Short stroke across a digit ring, counting 1 to 4
+pub struct DigitLine {
+    loc: GallRelLoc,
+    pub length: f64,
+    pub thickness: f64,
+}
+
+impl DigitLine {
+    pub fn new(loc:GallRelLoc, length:f64, thickness:f64) -> DigitLine {
+        DigitLine {
+            loc,
+            length,
+            thickness,
+        }
+    }
+    //follows the digit ring when its letter moves
+    pub fn update(&mut self) {
+        self.loc.update()
+    }
+    //inner and outer end of the stroke
+    pub fn ends(&self) -> ((f64,f64),(f64,f64)) {
+        let (x, y) = self.loc.get_center().get();
+        let (sin, cos) = self.loc.ang().unwrap_or(0.0).sin_cos();
+        let (inner, outer) = (self.loc.dist() - self.length/2.0, self.loc.dist() + self.length/2.0);
+        ((x + inner*sin, y + inner*cos), (x + outer*sin, y + outer*cos))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(negative:bool, digits:&[u8], point:Option<usize>, groups:&[usize]) -> GallNumber {
+        GallNumber {
+            negative,
+            digits: digits.to_vec(),
+            point,
+            groups: groups.to_vec(),
+        }
+    }
+
+    #[test]
+    fn parses_signs_points_and_groups() {
+        assert_eq!(GallNumber::parse("-3.14"), Some(number(true, &[3, 1, 4], Some(1), &[])));
+        assert_eq!(GallNumber::parse("1,000"), Some(number(false, &[1, 0, 0, 0], None, &[1])));
+        assert_eq!(GallNumber::parse("0"), Some(number(false, &[0], None, &[])));
+        assert_eq!(GallNumber::parse("-1,024.5"), Some(number(true, &[1, 0, 2, 4, 5], Some(4), &[1])));
+    }
+
+    #[test]
+    fn rejects_bare_marks() {
+        //a point needs a digit before it, so ".5" lexes as a period and 5
+        assert_eq!(GallNumber::parse(".5"), None);
+        for run in ["", "-", "5.", "1,", "1.2.3", "1a"] {
+            assert_eq!(GallNumber::parse(run), None, "{}", run);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for run in ["0", "42", "-3.14", "1,000", "-1,024.5", "1,000,000.25", "007"] {
+            let parsed = GallNumber::parse(run).unwrap();
+            assert_eq!(parsed.to_string(), run);
+            assert_eq!(GallNumber::parse(&parsed.to_string()), Some(parsed));
+        }
+    }
+}
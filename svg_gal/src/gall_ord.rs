@@ -14,7 +14,10 @@ pub trait PolarOrdinate {
     fn mut_ccw(&mut self, angle:f64) -> Result<(),Error> {
         match self.ang() {
             None => Err(Error::new(GallError::AngleUndefined)),
-            Some(ang) => Ok(self.mut_ang(angle + ang))
+            Some(ang) => {
+                self.mut_ang(angle + ang);
+                Ok(())
+            }
         }
     }
     fn mut_cw(&mut self, angle:f64) -> Result<(),Error> {
@@ -51,7 +54,7 @@ impl GallOrd {
         } else {
             match self.ang() {
                 Some(_) => Ok(()),
-                None => return Err(Error::new(GallError::AngleUndefined))
+                None => Err(Error::new(GallError::AngleUndefined))
             }
         }
     }    
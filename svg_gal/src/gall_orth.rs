@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gall_config::Config;
+use crate::gall_errors::{Error, GallError};
+use crate::gall_stem::StemType;
+use crate::gall_vowel::VowelType;
+
+//Built-in tables, picked by name with the `orthography` config key
+pub const PRESETS: [(&str, &str); 5] = [
+    ("english", include_str!("orthography/english.toml")),
+    ("sherman-ck", include_str!("orthography/sherman-ck.toml")),
+    ("spanish", include_str!("orthography/spanish.toml")),
+    ("german", include_str!("orthography/german.toml")),
+    ("french", include_str!("orthography/french.toml")),
+];
+//How deep `base` tables and `as` aliases may nest before we call it a loop
+const MAX_NESTING: usize = 8;
+const MAX_MARKS: i8 = 4;
+
+//What one grapheme is drawn as
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct LetterClass {
+    pub stem: Option<StemType>,
+    pub vowel: Option<VowelType>,
+    pub dots: i8,
+    pub dashes: i8,
+}
+
+//A grapheme and the letters it is written with, more than one for aliases
+#[derive(Clone, Debug)]
+pub struct Grapheme {
+    pub letters: Vec<(String, LetterClass)>,
+    pub tight: bool,
+}
+
+//One `[letters]` entry of a table file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stem: Option<StemType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vowel: Option<VowelType>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    dots: i8,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    dashes: i8,
+    #[serde(default, rename = "as", skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    tight: bool,
+}
+
+//The letters of a table with its `base` tables merged in, as recorded in a
+//render's metadata so it reads back without the table file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Table(BTreeMap<String, Entry>);
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TableFile {
+    base: Option<String>,
+    #[serde(default)]
+    remove: Vec<String>,
+    #[serde(default)]
+    letters: HashMap<String, Entry>,
+}
+
+//Grapheme table used by the lexer. Keys are lowercase and may be several letters long.
+pub struct Orthography {
+    graphemes: HashMap<String, Grapheme>,
+    longest: usize,
+}
+
+impl Orthography {
+    //The configured table with `disabled_digraphs` taken out
+    pub fn from_config(config:&Config) -> Result<Orthography, Error> {
+        let mut orth = Orthography::load(&config.orthography)?;
+        orth.disable(&config.disabled_digraphs)?;
+        Ok(orth)
+    }
+    //`name` is a preset or the path of a TOML table
+    pub fn load(name:&str) -> Result<Orthography, Error> {
+        Orthography::from_table(name, &Table::load(name)?)
+    }
+    //`name` only goes into error messages
+    pub fn from_table(name:&str, table:&Table) -> Result<Orthography, Error> {
+        let entries = &table.0;
+        let mut graphemes = HashMap::new();
+        for (key, entry) in entries {
+            check_key(name, key)?;
+            let letters = match &entry.alias {
+                Some(_) if entry.stem.is_some() || entry.vowel.is_some() || entry.dots != 0 || entry.dashes != 0 => {
+                    Err("an alias can't also have a stem, vowel, dots or dashes".to_string())
+                },
+                Some(alias) => spell(alias, entries, 0),
+                None => classify(entry).map(|class| vec![(key.clone(), class)]),
+            }.map_err(|e| invalid(name, &format!("`{}`: {}", key, e)))?;
+            graphemes.insert(key.clone(), Grapheme{letters, tight:entry.tight});
+        }
+        let longest = graphemes.keys().map(|key| key.chars().count()).max().unwrap_or(1);
+        Ok(Orthography {
+            graphemes,
+            longest,
+        })
+    }
+    //Disabled graphemes are read as their single letters instead
+    pub fn disable(&mut self, digraphs:&[String]) -> Result<(), Error> {
+        for digraph in digraphs {
+            let key = digraph.to_lowercase();
+            if key.chars().count() < 2 || self.graphemes.remove(&key).is_none() {
+                return Err(Error::new(GallError::InvalidConfig(format!(
+                    "disabled_digraphs: `{}` is not a digraph of the orthography", digraph
+                ))));
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self, key:&str) -> Option<&Grapheme> {
+        self.graphemes.get(key)
+    }
+    //Shortest grapheme drawn as `class`, for reading a drawing back
+    pub fn spelling(&self, class:&LetterClass) -> Option<&str> {
+        self.graphemes.iter()
+            .filter(|(key, grapheme)| grapheme.letters.len() == 1 && grapheme.letters[0] == ((*key).clone(), *class))
+            .map(|(key, _)| key.as_str())
+            .min_by_key(|key| (key.chars().count(), *key))
+    }
+    //The grapheme starting at `pos` and how many characters it takes up
+    pub fn grapheme_at(&self, chars:&[char], pos:usize) -> Option<(&Grapheme, usize)> {
+        let len = match_len(chars, pos, self.longest, |key| self.get(key).map(|grapheme| grapheme.tight))?;
+        let key:String = chars[pos..pos + len].iter().collect();
+        self.get(&key).map(|grapheme| (grapheme, len))
+    }
+}
+
+//Longest grapheme at `pos`, unless it is loose and a tight one starts inside it
+//and runs past its end, so "month" is m-o-n-th rather than m-o-nt-h.
+//`tight` gives None for unknown keys.
+fn match_len<F>(chars:&[char], pos:usize, longest:usize, tight:F) -> Option<usize>
+where F: Fn(&str) -> Option<bool> {
+    let key = |start:usize, end:usize| chars[start..end].iter().collect::<String>();
+    let max = longest.min(chars.len() - pos);
+    (1..=max).rev().find(|&len| {
+        let end = pos + len;
+        match tight(&key(pos, end)) {
+            Some(true) => true,
+            Some(false) => len == 1 || !(pos + 1..end).any(|start| {
+                (end + 1..=chars.len().min(start + longest)).any(|over| tight(&key(start, over)) == Some(true))
+            }),
+            None => false,
+        }
+    })
+}
+
+impl Table {
+    //`name` is a preset or the path of a TOML table
+    pub fn load(name:&str) -> Result<Table, Error> {
+        load_entries(name, 0).map(Table)
+    }
+}
+
+pub fn is_preset(name:&str) -> bool {
+    PRESETS.iter().any(|(preset, _)| *preset == name)
+}
+
+fn load_entries(name:&str, depth:usize) -> Result<BTreeMap<String, Entry>, Error> {
+    if depth > MAX_NESTING {
+        return Err(invalid(name, "too many nested base tables"));
+    }
+    let text = match PRESETS.iter().find(|(preset, _)| *preset == name) {
+        Some((_, text)) => text.to_string(),
+        None => fs::read_to_string(name)
+            .map_err(|e| {
+                let presets:Vec<&str> = PRESETS.iter().map(|(preset, _)| *preset).collect();
+                Error::new(GallError::ConfigRead(format!("{}: {} (presets are {})", name, e, presets.join(", "))))
+            })?,
+    };
+    let table:TableFile = toml::from_str(&text).map_err(|e| invalid(name, e.message()))?;
+    let mut entries = match &table.base {
+        Some(base) => load_entries(&base_path(name, base), depth + 1)?,
+        None => BTreeMap::new(),
+    };
+    for key in &table.remove {
+        entries.remove(key);
+    }
+    for (key, entry) in table.letters {
+        check_key(name, &key)?;
+        entries.insert(key, entry);
+    }
+    Ok(entries)
+}
+
+//A file `base` is found next to the table naming it, not in the working directory
+fn base_path(name:&str, base:&str) -> String {
+    match Path::new(name).parent() {
+        Some(dir) if !is_preset(name) && !is_preset(base) => dir.join(base).to_string_lossy().into_owned(),
+        _ => base.to_string(),
+    }
+}
+
+fn check_key(name:&str, key:&str) -> Result<(), Error> {
+    if key.is_empty() || key.chars().any(|cha| !cha.is_alphabetic() || cha.is_uppercase()) {
+        return Err(invalid(name, &format!("`{}` must be lowercase letters", key)));
+    }
+    Ok(())
+}
+
+fn classify(entry:&Entry) -> Result<LetterClass, String> {
+    let class = LetterClass {
+        stem: entry.stem,
+        vowel: entry.vowel,
+        dots: entry.dots,
+        dashes: entry.dashes,
+    };
+    if class.stem.is_some() == class.vowel.is_some() {
+        return Err("needs exactly one of stem, vowel or as".to_string());
+    }
+    if !(0..=MAX_MARKS).contains(&class.dots) || !(0..=MAX_MARKS).contains(&class.dashes) {
+        return Err(format!("dots and dashes must be between 0 and {}", MAX_MARKS));
+    }
+    if class.dots > 0 && class.dashes > 0 {
+        return Err("can't have both dots and dashes".to_string());
+    }
+    match class.vowel {
+        Some(_) if class.dots > 0 => Err("vowels can't have dots".to_string()),
+        Some(VowelType::I|VowelType::U) => Ok(class),
+        Some(_) if class.dashes > 0 => Err("only i and u vowels have a dash".to_string()),
+        _ => Ok(class),
+    }
+}
+
+//Reads an alias into the letters it stands for, following further aliases
+fn spell(text:&str, entries:&BTreeMap<String, Entry>, depth:usize) -> Result<Vec<(String, LetterClass)>, String> {
+    if depth > MAX_NESTING {
+        return Err("aliases loop back on themselves".to_string());
+    }
+    let longest = entries.keys().map(|key| key.chars().count()).max().unwrap_or(1);
+    let chars:Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut letters = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let len = match_len(&chars, pos, longest, |key| entries.get(key).map(|entry| entry.tight))
+            .ok_or_else(|| format!("no letter for '{}' in `{}`", chars[pos], text))?;
+        let key:String = chars[pos..pos + len].iter().collect();
+        let entry = &entries[&key];
+        match &entry.alias {
+            Some(alias) => letters.extend(spell(alias, entries, depth + 1)?),
+            None => letters.push((key, classify(entry)?)),
+        }
+        pos += len;
+    }
+    Ok(letters)
+}
+
+//Plain letters for accented and joined ones the table doesn't know
+pub fn fold(cha:char) -> Option<&'static str> {
+    let folded = match cha {
+        'à'|'á'|'â'|'ã'|'ä'|'å'|'ā'|'ă'|'ą' => "a",
+        'ç'|'ć'|'č'|'ĉ'|'ċ' => "c",
+        'ď'|'đ'|'ð' => "d",
+        'è'|'é'|'ê'|'ë'|'ē'|'ĕ'|'ė'|'ę'|'ě' => "e",
+        'ĝ'|'ğ'|'ġ'|'ģ' => "g",
+        'ĥ'|'ħ' => "h",
+        'ì'|'í'|'î'|'ï'|'ĩ'|'ī'|'ĭ'|'į'|'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ'|'ļ'|'ľ'|'ŀ'|'ł' => "l",
+        'ñ'|'ń'|'ņ'|'ň' => "n",
+        'ò'|'ó'|'ô'|'õ'|'ö'|'ø'|'ō'|'ŏ'|'ő' => "o",
+        'ŕ'|'ŗ'|'ř' => "r",
+        'ś'|'ŝ'|'ş'|'š'|'ș' => "s",
+        'ţ'|'ť'|'ŧ'|'ț' => "t",
+        'ù'|'ú'|'û'|'ü'|'ũ'|'ū'|'ŭ'|'ů'|'ű'|'ų' => "u",
+        'ŵ' => "w",
+        'ý'|'ÿ'|'ŷ' => "y",
+        'ź'|'ż'|'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        'ĳ' => "ij",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+fn is_zero(count:&i8) -> bool {
+    *count == 0
+}
+fn is_false(flag:&bool) -> bool {
+    !flag
+}
+
+fn invalid(name:&str, message:&str) -> Error {
+    Error::new(GallError::InvalidConfig(format!("orthography {}: {}", name, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_base_is_next_to_its_table() {
+        let dir = std::env::temp_dir().join(format!("svg_gal_orth_{}", std::process::id()));
+        fs::create_dir_all(dir.join("more")).unwrap();
+        fs::write(dir.join("root.toml"), "base = \"english\"\n[letters]\nx = { stem = \"b\" }\n").unwrap();
+        fs::write(dir.join("more/child.toml"), "base = \"../root.toml\"\n[letters]\nq = { stem = \"s\" }\n").unwrap();
+        let table = Table::load(dir.join("more/child.toml").to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        let Table(entries) = table.unwrap();
+        assert!(entries.contains_key("x") && entries.contains_key("q") && entries.contains_key("th"));
+    }
+}
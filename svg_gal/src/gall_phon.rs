@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::gall_config::Config;
+use crate::gall_errors::{Error, GallError};
+use crate::gall_lex::SEPARATOR;
+use crate::gall_mark::PunctType;
+use crate::gall_orth;
+
+//Small CMUdict style word list, `pronunciations` adds to or overrides it
+const BUNDLED: &str = include_str!("phonetic/words.dict");
+//Wraps a word given as IPA, e.g. "/θɪŋk/"
+pub const IPA_MARK: char = '/';
+//Sounds are spelt with English graphemes, so they are read with this table
+//whatever the orthography
+pub const SOUNDS: &str = "english";
+
+//Looks up how English words sound, as ARPAbet phonemes without stress
+pub struct Pronouncer {
+    words: HashMap<String, Vec<String>>,
+}
+
+impl Pronouncer {
+    //None unless phonetic mode is on
+    pub fn from_config(config:&Config) -> Result<Option<Pronouncer>, Error> {
+        if !config.phonetic {
+            return Ok(None)
+        }
+        let mut phon = Pronouncer::parse(BUNDLED);
+        if !config.pronunciations.is_empty() {
+            let text = fs::read_to_string(&config.pronunciations)
+                .map_err(|e| Error::new(GallError::ConfigRead(format!("{}: {}", config.pronunciations, e))))?;
+            phon.words.extend(Pronouncer::parse(&text).words);
+        }
+        Ok(Some(phon))
+    }
+    //CMUdict lines are "WORD  P1 P2 ...", ";;;" starts a comment and only
+    //the first pronunciation of a word is kept
+    pub fn parse(text:&str) -> Pronouncer {
+        let mut words = HashMap::new();
+        for line in text.lines().filter(|line| !line.starts_with(";;;")) {
+            let mut parts = line.split_whitespace();
+            let Some(word) = parts.next().filter(|word| !word.ends_with(')')) else {
+                continue;
+            };
+            let phonemes = parts.map(|phoneme| phoneme.trim_end_matches(|cha:char| cha.is_ascii_digit()).to_string()).collect();
+            words.entry(word.to_lowercase()).or_insert(phonemes);
+        }
+        Pronouncer {
+            words,
+        }
+    }
+    //Dictionary first, spelling rules for anything it doesn't have
+    pub fn phonemes(&self, word:&str) -> Vec<String> {
+        match self.words.get(&word.to_lowercase()) {
+            Some(phonemes) => phonemes.clone(),
+            None => rule_phonemes(word).into_iter().map(str::to_string).collect(),
+        }
+    }
+}
+
+//The word's letters spelt out by sound, one grapheme of `SOUNDS` per phoneme with
+//separators between them. None if the word should be read as written: numbers, or any word
+//outside phonetic mode unless it is IPA.
+pub fn respell(raw_word:&str, phon:Option<&Pronouncer>) -> Option<String> {
+    let core = raw_word.trim_matches(|cha| PunctType::lookup(&cha).is_some());
+    let phonemes = match core.strip_prefix(IPA_MARK).and_then(|ipa| ipa.strip_suffix(IPA_MARK)) {
+        Some(ipa) => ipa_phonemes(ipa),
+        None => {
+            let phon = phon?;
+            if core.chars().any(|cha| cha.is_ascii_digit()) || !core.chars().any(char::is_alphabetic) {
+                return None
+            }
+            phon.phonemes(core)
+        },
+    };
+    let graphemes:Vec<&str> = phonemes.iter().flat_map(|phoneme| spell(phoneme)).copied().collect();
+    Some(graphemes.join(&SEPARATOR.to_string()))
+}
+
+//English letters drawn for each phoneme. Diphthongs are written as two vowels.
+fn spell(phoneme:&str) -> &'static [&'static str] {
+    match phoneme {
+        "AA"|"AE" => &["a"],
+        "AH"|"UH"|"UW" => &["u"],
+        "AO"|"OW" => &["o"],
+        "AW" => &["a", "u"],
+        "AY" => &["a", "i"],
+        "EH"|"IY" => &["e"],
+        "ER" => &["e", "r"],
+        "EY" => &["e", "i"],
+        "IH" => &["i"],
+        "OY" => &["o", "i"],
+        "B" => &["b"],
+        "CH" => &["ch"],
+        "D" => &["d"],
+        "DH"|"TH" => &["th"],
+        "F" => &["f"],
+        "G" => &["g"],
+        "HH" => &["h"],
+        "JH" => &["j"],
+        "K" => &["k"],
+        "L" => &["l"],
+        "M" => &["m"],
+        "N" => &["n"],
+        "NG" => &["ng"],
+        "P" => &["p"],
+        "R" => &["r"],
+        "S" => &["s"],
+        "SH"|"ZH" => &["sh"],
+        "T" => &["t"],
+        "V" => &["v"],
+        "W" => &["w"],
+        "Y" => &["y"],
+        "Z" => &["z"],
+        _ => &[],
+    }
+}
+
+//IPA symbols as ARPAbet, two character ones first. Stress, length and
+//syllable marks are skipped, as is anything unknown.
+fn ipa_phonemes(ipa:&str) -> Vec<String> {
+    let chars:Vec<char> = ipa.chars()
+        .filter(|cha| !matches!(cha, 'ˈ'|'ˌ'|'ː'|'ˑ'|'.'|'‿'|'\u{0300}'..='\u{036f}'))
+        .collect();
+    let mut phonemes = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let pair = chars.get(pos + 1).map(|next| (chars[pos], *next));
+        let double = match pair {
+            Some(('t','ʃ')) => Some("CH"),
+            Some(('d','ʒ')) => Some("JH"),
+            Some(('a','ɪ')) => Some("AY"),
+            Some(('a','ʊ')) => Some("AW"),
+            Some(('ɔ','ɪ')) => Some("OY"),
+            Some(('e','ɪ')) => Some("EY"),
+            Some(('o'|'ə','ʊ')) => Some("OW"),
+            _ => None,
+        };
+        if let Some(phoneme) = double {
+            phonemes.push(phoneme.to_string());
+            pos += 2;
+            continue;
+        }
+        let single = match chars[pos] {
+            'p' => "P", 'b' => "B", 't'|'ɾ' => "T", 'd' => "D", 'k'|'x' => "K", 'g'|'ɡ' => "G",
+            'f' => "F", 'v' => "V", 'θ' => "TH", 'ð' => "DH", 's' => "S", 'z' => "Z",
+            'ʃ' => "SH", 'ʒ' => "ZH", 'h' => "HH", 'm' => "M", 'n' => "N", 'ŋ' => "NG",
+            'l'|'ɫ' => "L", 'r'|'ɹ'|'ʁ'|'ɻ' => "R", 'j' => "Y", 'w'|'ʍ' => "W",
+            'ʧ' => "CH", 'ʤ' => "JH",
+            'ɑ'|'a'|'ɒ' => "AA", 'æ' => "AE", 'ʌ'|'ə'|'ɐ' => "AH", 'ɛ'|'e' => "EH",
+            'ɪ' => "IH", 'i' => "IY", 'ʊ' => "UH", 'u' => "UW", 'ɔ' => "AO", 'o' => "OW",
+            'ɜ'|'ɝ'|'ɚ' => "ER",
+            _ => "",
+        };
+        if !single.is_empty() {
+            phonemes.push(single.to_string());
+        }
+        pos += 1;
+    }
+    phonemes
+}
+
+//Rough English spelling rules for words missing from the dictionary
+fn rule_phonemes(word:&str) -> Vec<&'static str> {
+    let letters:Vec<char> = word.chars()
+        .flat_map(char::to_lowercase)
+        .flat_map(|cha| match gall_orth::fold(cha) {
+            Some(folded) => folded.chars().collect(),
+            None => vec![cha],
+        })
+        .filter(char::is_ascii_lowercase)
+        .collect();
+    let len = letters.len();
+    let is_vowel = |cha:char| matches!(cha, 'a'|'e'|'i'|'o'|'u');
+    let at = |pos:usize, pattern:&str| pattern.chars().enumerate().all(|(n, cha)| letters.get(pos + n) == Some(&cha));
+    let mut phonemes = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        let cha = letters[pos];
+        //silent final e, and the second of a doubled consonant
+        if (pos == len - 1 && cha == 'e' && len > 2 && !is_vowel(letters[pos - 1]))
+        || (pos > 0 && cha == letters[pos - 1] && !is_vowel(cha)) {
+            pos += 1;
+            continue;
+        }
+        let patterns:[(&str, &[&'static str]); 27] = [
+            ("tch", &["CH"]), ("igh", &["AY"]), ("dge", &["JH"]),
+            ("ch", &["CH"]), ("sh", &["SH"]), ("th", &["TH"]), ("ph", &["F"]), ("wh", &["W"]),
+            ("ck", &["K"]), ("ng", &["NG"]), ("qu", &["K", "W"]), ("gh", &[]),
+            ("ee", &["IY"]), ("ea", &["IY"]), ("oo", &["UW"]), ("ou", &["AW"]), ("ow", &["OW"]),
+            ("oi", &["OY"]), ("oy", &["OY"]), ("ai", &["EY"]), ("ay", &["EY"]),
+            ("au", &["AO"]), ("aw", &["AO"]), ("ar", &["AA", "R"]), ("er", &["ER"]),
+            ("ir", &["ER"]), ("ur", &["ER"]),
+        ];
+        if pos == 0 && (at(0, "kn") || at(0, "wr")) {
+            phonemes.push(if cha == 'k' {"N"} else {"R"});
+            pos += 2;
+            continue;
+        }
+        if let Some((pattern, sounds)) = patterns.iter().find(|(pattern, _)| at(pos, pattern)) {
+            //a leading gh is still a g, as in "ghost"
+            phonemes.extend_from_slice(if *pattern == "gh" && pos == 0 {&["G"]} else {sounds});
+            pos += pattern.len();
+            continue;
+        }
+        let next = letters.get(pos + 1).copied();
+        //vowel, consonant, final e makes the vowel long
+        let long = pos + 3 == len && next.is_some_and(|next| !is_vowel(next)) && letters[len - 1] == 'e';
+        let sounds:&[&'static str] = match cha {
+            'a' => if long {&["EY"]} else {&["AE"]},
+            'e' => if long {&["IY"]} else {&["EH"]},
+            'i' => if long {&["AY"]} else {&["IH"]},
+            'o' => if long {&["OW"]} else {&["AA"]},
+            'u' => if long {&["UW"]} else {&["AH"]},
+            'c' if matches!(next, Some('e'|'i'|'y')) => &["S"],
+            'c'|'k'|'q' => &["K"],
+            'x' => &["K", "S"],
+            'y' if pos == 0 => &["Y"],
+            'y' if pos == len - 1 => &["IY"],
+            'y' => &["IH"],
+            'b' => &["B"], 'd' => &["D"], 'f' => &["F"], 'g' => &["G"], 'h' => &["HH"],
+            'j' => &["JH"], 'l' => &["L"], 'm' => &["M"], 'n' => &["N"], 'p' => &["P"],
+            'r' => &["R"], 's' => &["S"], 't' => &["T"], 'v' => &["V"], 'w' => &["W"],
+            'z' => &["Z"],
+            _ => &[],
+        };
+        phonemes.extend_from_slice(sounds);
+        pos += 1;
+    }
+    phonemes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipa(ipa:&str) -> String {
+        ipa_phonemes(ipa).join(" ")
+    }
+    fn rules(word:&str) -> String {
+        rule_phonemes(word).join(" ")
+    }
+
+    #[test]
+    fn ipa_pairs_before_singles() {
+        assert_eq!(ipa("θɪŋk"), "TH IH NG K");
+        assert_eq!(ipa("tʃeɪndʒ"), "CH EY N JH");
+        assert_eq!(ipa("ʤʌʧ"), "JH AH CH");
+    }
+
+    #[test]
+    fn ipa_marks_and_unknowns_skipped() {
+        assert_eq!(ipa("ˈhɛˌloʊ"), "HH EH L OW");
+        assert_eq!(ipa("biː.tʰ"), "B IY T");
+    }
+
+    #[test]
+    fn rule_digraphs() {
+        assert_eq!(rules("knight"), "N AY T");
+        assert_eq!(rules("phone"), "F OW N");
+        assert_eq!(rules("watch"), "W AE CH");
+        assert_eq!(rules("ghost"), "G AA S T");
+    }
+
+    #[test]
+    fn rule_vowels_and_doubles() {
+        assert_eq!(rules("cake"), "K EY K");
+        assert_eq!(rules("running"), "R AH N IH NG");
+        assert_eq!(rules("city"), "S IH T IY");
+        assert_eq!(rules("Café"), "K EY F");
+    }
+
+    #[test]
+    fn dictionary_lookup() {
+        let phon = Pronouncer::parse(";;; comment\nREAD  R IY1 D\nREAD(2)  R EH1 D\nLIVE  L IH1 V\nLIVE  L AY1 V\n");
+        assert_eq!(phon.phonemes("Read"), ["R", "IY", "D"]);
+        //only the first of two lines for a word counts
+        assert_eq!(phon.phonemes("live"), ["L", "IH", "V"]);
+        assert_eq!(phon.phonemes("cake"), ["K", "EY", "K"]);
+        let bundled = Pronouncer::parse(BUNDLED);
+        assert_eq!(bundled.phonemes("about"), ["AH", "B", "AW", "T"]);
+    }
+
+    #[test]
+    fn respelt_words() {
+        let phon = Pronouncer::parse(BUNDLED);
+        assert_eq!(respell("/θɪŋk/", None).as_deref(), Some("th|i|ng|k"));
+        assert_eq!(respell("\"about,", Some(&phon)).as_deref(), Some("u|b|a|u|t"));
+        assert_eq!(respell("r2d2", Some(&phon)), None);
+        assert_eq!(respell("about", None), None);
+    }
+}
@@ -1,11 +1,15 @@
 use std::cell::Cell;
+use std::f64::consts::TAU;
 use std::rc::Rc;
 
+use crate::gall_ang;
 use crate::gall_circle::{Circle, HollowCircle};
 use crate::gall_config::Config;
 use crate::gall_errors::Error;
-use crate::gall_fn::{self, ProcessedWord};
+use crate::gall_fn::{self, ProcessedWord, MAX_WORD_RADIUS};
 use crate::gall_loc::{GallLoc, LocHolder, Location};
+use crate::gall_mark::{GallMark, PunctType};
+use crate::gall_meta::{self, SentenceMeta};
 use crate::gall_node::GallNode;
 use crate::gall_ord::{GallOrd, OrdHolder, PolarOrdinate};
 use crate::gall_word::GallWord;
@@ -13,6 +17,10 @@ use crate::gall_word::GallWord;
 pub struct GallSentence {
     loc: GallLoc,
     pub words:Vec<GallWord>,
+    pub marks:Vec<GallMark>,
+    pub meta: SentenceMeta,
+    //the phrase as given, for labelling the render
+    pub phrase: String,
     //pub dash_pairs: Vec<CircleGallLine>,
     radius:Rc<Cell<f64>>,
     thickness: Rc<Cell<f64>>,
@@ -23,32 +31,217 @@ impl GallSentence {
         GallSentence{
             loc,
             words: Vec::new(),
+            marks: Vec::new(),
+            meta: SentenceMeta::default(),
+            phrase: String::new(),
             radius: Rc::new(Cell::new(radius)),
             thickness: Rc::new(Cell::new(thickness)),
         }
     }
-    pub fn generate(&mut self, word_list:Vec<ProcessedWord>) {
+    pub fn generate(&mut self, mut word_list:Vec<ProcessedWord>, config:&Config) -> Result<(), Error> {
         let sentence_length = word_list.len();
-        let ang = gall_fn::basic_angle(&word_list, 6, 10, 14);
-        for (num,word) in word_list.into_iter().enumerate() {
-            let (w_radius, w_thick, word_ang, dist) = if sentence_length == 1 {
-                gall_fn::default_layouts(sentence_length, num)
-            } else if Config::BASIC_LAYOUT {
-                gall_fn::default_layouts(sentence_length, num)
-            } else {
-                (300.0,25.0,ang, 500.0)
-            };
+        self.meta = SentenceMeta::new(&word_list, config)?;
+        let layouts = gall_fn::word_layouts(config.layout, &word_list, config);
+        //a trial build at the layout size tells how far each word reaches
+        let reach = word_list.iter().zip(&layouts).map(|(word, &(w_radius, w_thick, _, _))| {
+            Ok(self.probe(word, w_radius, w_thick, config)?.extent()/w_radius)
+        }).collect::<Result<Vec<f64>, Error>>()?;
+        let layouts = self.relax(&mut word_list, layouts, &reach, config)?;
+        let mut punct_list = Vec::with_capacity(sentence_length);
+        for (index, (mut word, (w_radius, w_thick, word_ang, dist))) in word_list.into_iter().zip(layouts).enumerate() {
+            let punct_after = word.punct_inside.drain(..).map(|(_, punct)| punct).chain(word.punct_after.drain(..)).collect();
+            punct_list.push((
+                std::mem::take(&mut word.punct_before),
+                punct_after,
+            ));
             //create word struct
             let loc = GallLoc::new(
                 word_ang,
                 dist,
                 self.pos_ref(),
             );
-            self.words.push(GallWord::new(word, loc, w_radius, w_thick));
+            self.words.push(GallWord::new(word, gall_meta::word_id(index), loc, w_radius, w_thick, config)?);
         }
         for wrd in &mut self.words {
             wrd.spread();
         }
+        if config.auto_radius {
+            self.fit_radius(config);
+        }
+        self.place_marks(punct_list, config);
+        Ok(())
+    }
+    //Pushes overlapping words apart and pulls stray ones back inside the
+    //sentence ring, shrinking every word while they can't all fit and
+    //growing them a little while there is room to spare.
+    //`reach` is each word's extent as a multiple of its radius. Sizes where
+    //a letter would miss its word line are passed over.
+    fn relax(&self, word_list:&mut [ProcessedWord], layouts:Vec<(f64,f64,f64,f64)>, reach:&[f64], config:&Config) -> Result<Vec<(f64,f64,f64,f64)>, Error> {
+        let room = self.inner_radius() - config.word_gap;
+        let start:Vec<(f64,f64)> = layouts.iter().map(|&(_, _, ang, dist)| {
+            let (sin, cos) = ang.sin_cos();
+            (dist*sin, dist*cos)
+        }).collect();
+        let mut reach = reach.to_vec();
+        for _ in 0..MAX_REMEASURE {
+            let Some((scale, pos)) = self.best_scale(word_list, &layouts, &reach, &start, config) else {break};
+            let sizes:Vec<(f64,f64)> = layouts.iter().map(|&(w_radius, w_thick, _, _)| (scale*w_radius, scale*w_thick)).collect();
+            //letters and gaps don't all scale with the word, so a reach is only
+            //exact at the size it was measured at. Measure again at this one.
+            let extents = self.measure(word_list, &sizes, config)?;
+            let mut trial = pos;
+            if settle(&mut trial, &extents, room, config.word_gap) {
+                return Ok(placed(&layouts, &sizes, trial))
+            }
+            for ((reach, extent), size) in reach.iter_mut().zip(extents).zip(&sizes) {
+                *reach = reach.max(extent/size.0);
+            }
+        }
+        self.fallback(word_list, &layouts, start, config)
+    }
+    //The largest scale, up to MAX_GROWTH steps up, at which the words settle
+    //by their reach and keep their letters on their word lines
+    fn best_scale(&self, word_list:&[ProcessedWord], layouts:&[(f64,f64,f64,f64)], reach:&[f64], start:&[(f64,f64)], config:&Config) -> Option<(f64,Vec<(f64,f64)>)> {
+        let room = self.inner_radius() - config.word_gap;
+        let mut pos = start.to_vec();
+        let mut scale = 1.0;
+        let mut grown = 0;
+        let mut best = None;
+        loop {
+            let extents:Vec<f64> = layouts.iter().zip(reach)
+                .map(|(&(w_radius, _, _, _), reach)| scale*w_radius*reach)
+                .collect();
+            let mut trial = pos.clone();
+            let fits = least_room(&extents, config.word_gap) <= room && settle(&mut trial, &extents, room, config.word_gap);
+            if fits && self.holds(word_list, layouts, scale, config) {
+                pos = trial.clone();
+                best = Some((scale, trial));
+                let next = scale*RELAX_GROWTH;
+                if grown == MAX_GROWTH || layouts.iter().any(|layout| next*layout.0 > MAX_WORD_RADIUS) {
+                    break;
+                }
+                scale = next;
+                grown += 1;
+            } else if fits || best.is_some() || scale < Config::COLLISION_DIST {
+                break;
+            } else {
+                pos = trial;
+                scale /= RELAX_GROWTH;
+            }
+        }
+        best
+    }
+    //When no one size both fits and keeps the letters on their word lines,
+    //each word takes the size nearest its layout's that keeps its own letters,
+    //unstacked if no size will do stacked. The sentence ring is made room for
+    //if `auto_radius` will fit it to the words.
+    fn fallback(&self, word_list:&mut [ProcessedWord], layouts:&[(f64,f64,f64,f64)], pos:Vec<(f64,f64)>, config:&Config) -> Result<Vec<(f64,f64,f64,f64)>, Error> {
+        let mut sizes = Vec::with_capacity(layouts.len());
+        for (word, &(w_radius, w_thick, _, _)) in word_list.iter_mut().zip(layouts) {
+            let mut size = self.nearest_size(word, w_radius, w_thick, config);
+            if size.is_none() && word.stack {
+                word.stack = false;
+                size = self.nearest_size(word, w_radius, w_thick, config);
+            }
+            //still nothing, so `layout` reports the letter off its word line
+            sizes.push(size.unwrap_or((w_radius, w_thick)));
+        }
+        let extents = self.measure(word_list, &sizes, config)?;
+        let mut room = extents.iter().fold(self.inner_radius() - config.word_gap, |room, &extent| room.max(extent));
+        if config.auto_radius {
+            room = room.max(least_room(&extents, config.word_gap));
+        }
+        let mut trial = pos.clone();
+        while !settle(&mut trial, &extents, room, config.word_gap) && config.auto_radius {
+            room *= RELAX_GROWTH;
+            trial = pos.clone();
+        }
+        Ok(placed(layouts, &sizes, trial))
+    }
+    //How far each word reaches when built at its size
+    fn measure(&self, word_list:&[ProcessedWord], sizes:&[(f64,f64)], config:&Config) -> Result<Vec<f64>, Error> {
+        word_list.iter().zip(sizes).map(|(word, &(w_radius, w_thick))| {
+            Ok(self.probe(word, w_radius, w_thick, config)?.extent())
+        }).collect()
+    }
+    //Radius and thickness in growth steps either side of the given ones,
+    //nearest first, at which the word keeps its letters on its word line
+    fn nearest_size(&self, word:&ProcessedWord, w_radius:f64, w_thick:f64, config:&Config) -> Option<(f64,f64)> {
+        (0..=MAX_RESIZE)
+            .flat_map(|step| [RELAX_GROWTH.powi(step), RELAX_GROWTH.powi(-step)])
+            .map(|scale| scale.min(MAX_WORD_RADIUS/w_radius))
+            .map(|scale| (scale*w_radius, scale*w_thick))
+            .find(|&(w_radius, w_thick)| self.fits_line(word, w_radius, w_thick, config))
+    }
+    //Whether every word keeps its letters on its word line at `scale` times its layout size
+    fn holds(&self, word_list:&[ProcessedWord], layouts:&[(f64,f64,f64,f64)], scale:f64, config:&Config) -> bool {
+        word_list.iter().zip(layouts).all(|(word, &(w_radius, w_thick, _, _))| {
+            self.fits_line(word, scale*w_radius, scale*w_thick, config)
+        })
+    }
+    //Whether `word` builds at this size with its letters on its word line
+    fn fits_line(&self, word:&ProcessedWord, w_radius:f64, w_thick:f64, config:&Config) -> bool {
+        self.probe(word, w_radius, w_thick, config).is_ok_and(|probe| probe.skel_check().is_ok())
+    }
+    //A stand-in for `word` at the sentence centre, built as it will be drawn
+    fn probe(&self, word:&ProcessedWord, w_radius:f64, w_thick:f64, config:&Config) -> Result<GallWord, Error> {
+        let probe_loc = GallLoc::new(0.0, 0.0, self.pos_ref());
+        let mut probe = GallWord::new(word.clone(), String::new(), probe_loc, w_radius, w_thick, config)?;
+        probe.spread();
+        probe.basic();
+        Ok(probe)
+    }
+    pub fn skel_check(&self) -> Result<(), Error> {
+        self.words.iter().try_for_each(GallWord::skel_check)
+    }
+    //Shrinks (or grows) the sentence ring to just clear its words
+    fn fit_radius(&mut self, config:&Config) {
+        let content = self.words.iter()
+            .map(|word| word.dist() + word.extent())
+            .fold(0.0, f64::max);
+        if content > 0.0 {
+            _ = self.mut_radius(content + config.word_gap + self.thick());
+        }
+    }
+    //How far anything drawn for the sentence reaches from its centre
+    pub fn reach(&self, config:&Config) -> f64 {
+        if self.marks.is_empty() {
+            self.outer_radius()
+        } else {
+            self.outer_radius().max(self.radius() + config.punct_radius + self.thick())
+        }
+    }
+    //Each gap between neighbouring words gets the trailing marks of the
+    //word before it and the leading marks of the word after it.
+    fn place_marks(&mut self, punct_list:Vec<(Vec<PunctType>,Vec<PunctType>)>, config:&Config) {
+        let count = self.words.len();
+        if count == 0 {
+            return;
+        }
+        let angles:Vec<f64> = self.words.iter().map(|word| word.ang().unwrap_or(0.0)).collect();
+        let mut gaps:Vec<Vec<PunctType>> = vec![Vec::new(); count];
+        for (num, (before, after)) in punct_list.into_iter().enumerate() {
+            gaps[num].extend(after);
+            gaps[(num + count - 1) % count].extend(before);
+        }
+        let step = 2.5*config.punct_radius/self.radius();
+        for (num, gap) in gaps.into_iter().enumerate() {
+            let start = angles[num];
+            let mut end = angles[(num + 1) % count];
+            while end <= start {
+                end += TAU;
+            }
+            let centre = (start + end)/2.0 - step*(gap.len() as f64 - 1.0)/2.0;
+            for (rank, punct) in gap.into_iter().enumerate() {
+                let loc = GallLoc::new(
+                    centre + step*rank as f64,
+                    self.radius(),
+                    self.pos_ref(),
+                );
+                let mark = GallMark::new(loc, config.punct_radius, punct, self);
+                self.marks.push(mark);
+            }
+        }
     }
     pub fn collect_nodes(&mut self) -> Vec<&mut GallNode> {
         let mut nodes = Vec::new();
@@ -65,6 +258,70 @@ impl GallSentence {
     }
 }
 
+const RELAX_STEPS: usize = 400;
+const RELAX_GROWTH: f64 = 1.05;
+const MAX_GROWTH: usize = 5;
+//how many growth steps either way a word may be resized on its own
+const MAX_RESIZE: i32 = 40;
+//how many times the words are measured at a picked size before falling back
+const MAX_REMEASURE: usize = 4;
+
+//Smallest room the words could fit in with no space wasted between them.
+//Their discs, gap included, can't cover more than the whole sentence disc,
+//so `settle` is only worth running from here up.
+fn least_room(extents:&[f64], gap:f64) -> f64 {
+    extents.iter().map(|extent| (extent + gap/2.0).powi(2)).sum::<f64>().sqrt() - gap/2.0
+}
+
+//The layouts with each word resized and moved to its new centre
+fn placed(layouts:&[(f64,f64,f64,f64)], sizes:&[(f64,f64)], pos:Vec<(f64,f64)>) -> Vec<(f64,f64,f64,f64)> {
+    layouts.iter().zip(sizes).zip(pos).map(|((&(_, _, ang, _), &(w_radius, w_thick)), (x, y))| {
+        let dist = x.hypot(y);
+        let ang = if dist > 0.0 {gall_ang::constrain(x.atan2(y))} else {ang};
+        (w_radius, w_thick, ang, dist)
+    }).collect()
+}
+
+//Moves the word centres until no two words (given by their extents) are
+//closer than `gap` and all lie within `room` of the sentence centre.
+//Returns false if that couldn't be reached.
+fn settle(pos:&mut [(f64,f64)], extents:&[f64], room:f64, gap:f64) -> bool {
+    if extents.iter().any(|&extent| extent > room) {
+        return false
+    }
+    let tolerance = Config::STEP_DIST;
+    for _ in 0..RELAX_STEPS {
+        let mut moved = false;
+        for i in 0..pos.len() {
+            for j in i+1..pos.len() {
+                let (dx, dy) = (pos[j].0-pos[i].0, pos[j].1-pos[i].1);
+                let dist = dx.hypot(dy);
+                let overlap = extents[i] + extents[j] + gap - dist;
+                if overlap > tolerance {
+                    //words on the same spot are split along an arbitrary but fixed direction
+                    let (ux, uy) = if dist > tolerance {(dx/dist, dy/dist)} else {(1.0, 0.0)};
+                    let push = overlap/2.0 + tolerance;
+                    pos[i] = (pos[i].0 - push*ux, pos[i].1 - push*uy);
+                    pos[j] = (pos[j].0 + push*ux, pos[j].1 + push*uy);
+                    moved = true;
+                }
+            }
+        }
+        for (spot, &extent) in pos.iter_mut().zip(extents) {
+            let dist = spot.0.hypot(spot.1);
+            if dist + extent > room + tolerance {
+                let pull = (room - extent)/dist;
+                *spot = (spot.0*pull, spot.1*pull);
+                moved = true;
+            }
+        }
+        if !moved {
+            return true
+        }
+    }
+    false
+}
+
 impl HollowCircle for GallSentence {
     fn thick(&self) -> f64 {
         self.thickness.get()
@@ -105,4 +362,34 @@ impl OrdHolder for GallSentence {
     fn mut_ord(&mut self) -> &mut GallOrd {
         &mut self.loc.ord
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //No two words touch and every word clears the sentence line by `word_gap`
+    fn assert_words_fit(phrase:&str, config:&Config) {
+        let sent = crate::layout(phrase, config).unwrap();
+        let tolerance = Config::STEP_DIST;
+        let words:Vec<((f64,f64), f64)> = sent.words.iter().map(|word| (word.svg_ord(), word.extent())).collect();
+        let (x, y) = sent.svg_ord();
+        for (n, &((wx, wy), extent)) in words.iter().enumerate() {
+            let outer = (wx-x).hypot(wy-y) + extent + config.word_gap;
+            assert!(outer <= sent.inner_radius() + tolerance, "word {} of \"{}\" reaches {} past {}", n, phrase, outer, sent.inner_radius());
+            for (m, &((ox, oy), other)) in words.iter().enumerate().skip(n + 1) {
+                let dist = (wx-ox).hypot(wy-oy);
+                assert!(dist + tolerance >= extent + other, "words {} and {} of \"{}\" overlap: {} apart, extents {} and {}", n, m, phrase, dist, extent, other);
+            }
+        }
+    }
+
+    #[test]
+    fn words_fit_without_touching() {
+        let config = Config::default();
+        let long = ["the quick brown fox jumps over the lazy dog"; 5].join(" ");
+        assert_words_fit(&long, &config);
+        //outward A vowels and B stems reach furthest past their word line
+        assert_words_fit("abba baba bab abab baa abb ba bababa abba", &config);
+        assert_words_fit("a ab aba abab ababa", &config);
+    }
+}
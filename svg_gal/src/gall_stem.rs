@@ -1,15 +1,29 @@
 use std::cell::Cell;
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+
 use crate::gall_errors::{Error, GallError};
 use crate::gall_fn;
 use crate::gall_loc::{GallLoc, Location};
 use crate::gall_circle::{ChildCircle, Circle, HollowCircle};
 use crate::gall_ord::PolarOrdinate;
 
-#[derive(PartialEq,Clone,Copy)]
+#[derive(PartialEq,Clone,Copy,Debug,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StemType {J,B,S,Z}
 
+impl StemType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StemType::J => "j",
+            StemType::B => "b",
+            StemType::S => "s",
+            StemType::Z => "z",
+        }
+    }
+}
+
 pub struct Stem {
     loc: GallLoc,
     radius: Rc<Cell<f64>>,
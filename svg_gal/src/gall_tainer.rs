@@ -1,26 +1,32 @@
 use std::cell::{Cell, OnceCell};
 use std::f64::consts::{PI, TAU};
+use std::ops::Range;
 use std::rc::Rc;
 
 use crate::gall_ang::GallAng;
-use crate::gall_circle::{ChildCircle, Circle, Dot, HollowCircle};
+use crate::gall_circle::{Circle, Dot, HollowCircle};
 use crate::gall_config::Config;
-use crate::gall_errors::{self, Error, GallError};
-use crate::gall_fn::{self, Decor, LetterMark};
+use crate::gall_errors::{Error, GallError};
+use crate::gall_fn::{Decor, LetterMark};
 use crate::gall_loc::{GallLoc, GallRelLoc, Location};
-use crate::gall_node::GallNode;
+use crate::gall_node::{GallNode, NodeKind};
+use crate::gall_num::{DigitLine, GallNumber};
 use crate::gall_ord::PolarOrdinate;
 use crate::gall_stem::{Stem, StemType};
 use crate::gall_vowel::{GallVowel, VowelType};
 use crate::gall_word::GallWord;
 
-struct TainerState {
+//Innermost digit ring, as a fraction of a letter's radius
+const NUMBER_CORE: f64 = 0.4;
+
+pub struct TainerState {
     angle: Rc<Cell<GallAng>>,
     stem_type: OnceCell<StemType>,
     letter_dist: Rc<Cell<f64>>, 
     letter_rad: Rc<Cell<f64>>, 
     letter_pos: Rc<Cell<(f64,f64)>>,
     vowel: bool,
+    number: bool,
 }
 
 pub struct GallTainer {
@@ -28,29 +34,45 @@ pub struct GallTainer {
     pub vowel: Vec<GallVowel>,
     pub node: Vec<GallNode>,
     pub dot: Vec<Dot>,
-    pub mark: Vec<()>, //GallMark>,
+    pub line: Vec<DigitLine>,
+    pub letters: Vec<LetterSpan>,
     pub state: Option<TainerState>,
 }
 
+//The shapes one letter added to a tainer, so the SVG can group them
+pub struct LetterSpan {
+    pub id: String,
+    pub text: String,
+    pub class: String,
+    pub stem: Range<usize>,
+    pub vowel: Range<usize>,
+    pub dot: Range<usize>,
+    pub node: Range<usize>,
+    pub line: Range<usize>,
+}
+
+//stem, vowel, dot, node and line counts
+pub type ShapeCounts = [usize; 5];
+
 impl TainerState {
-    pub fn new(angle: f64, letter_mark:&LetterMark, word:&GallWord) -> TainerState {
+    pub fn new(angle: f64, letter_mark:&LetterMark, word:&GallWord, config:&Config) -> TainerState {
         //let angle = ;
         let stem_type = OnceCell::new();
         let w_rad = word.radius();
         let rad: f64;
         let dist = match letter_mark {
             LetterMark::Stem(stem) => {
-                stem_type.set(*stem);
-                rad = word.radius()*Config::LETTER_FRAC_OF_WRD;
+                _ = stem_type.set(*stem);
+                rad = word.radius()*config.letter_frac_of_wrd;
                 match stem {
-                    StemType::J => w_rad*(0.7 - Config::LETTER_FRAC_OF_WRD),
-                    StemType::B => w_rad*(1.2 - Config::LETTER_FRAC_OF_WRD),
-                    StemType::S => w_rad + word.thick()*Config::LETTER_THICK_FRAC,
+                    StemType::J => w_rad*(0.7 - config.letter_frac_of_wrd),
+                    StemType::B => w_rad*(1.2 - config.letter_frac_of_wrd),
+                    StemType::S => w_rad + word.thick()*config.letter_thick_frac,
                     StemType::Z => w_rad,
                 }
             },
             LetterMark::GallVowel(vow) => {
-                rad = word.radius()*Config::VOWEL_FRAC_OF_WRD;
+                rad = word.radius()*config.vowel_frac_of_wrd;
                 match vow {
                     VowelType::A => w_rad*1.2,
                     VowelType::O1 => w_rad*0.6,
@@ -58,12 +80,15 @@ impl TainerState {
                     _ => w_rad,
                 }
             },
-            LetterMark::Digit(_) => {
-                stem_type.set(StemType::J);
-                rad = word.radius()*Config::LETTER_FRAC_OF_WRD;
-                w_rad*(0.7 - Config::LETTER_FRAC_OF_WRD)
+            //no stem type, so letters never stack onto a number
+            LetterMark::Number(_) => {
+                rad = word.radius()*config.letter_frac_of_wrd*NUMBER_CORE;
+                w_rad*(0.7 - config.letter_frac_of_wrd)
+            },
+            LetterMark::GallMark => {
+                rad = 0.0;
+                w_rad
             },
-            LetterMark::GallMark => todo!(),
         };
 
         let loc = GallLoc::new(
@@ -78,10 +103,17 @@ impl TainerState {
             letter_rad: Rc::new(Cell::new(rad)), 
             letter_pos: loc.pos_ref(), 
             vowel: false,
+            number: matches!(letter_mark, LetterMark::Number(_)),
         }
     }
 }
 
+impl Default for GallTainer {
+    fn default() -> Self {
+        GallTainer::new()
+    }
+}
+
 impl GallTainer {
     pub fn new() -> GallTainer {
         GallTainer {
@@ -89,23 +121,45 @@ impl GallTainer {
             vowel:Vec::new(),
             node: Vec::new(),
             dot: Vec::new(),
-            mark: Vec::new(),
+            line: Vec::new(),
+            letters: Vec::new(),
             state: None,
         }
     }
-    pub fn init(&mut self, mark:&LetterMark, con_count:usize, ang:f64, word: &GallWord) -> usize {
-        if let Some(state) = &mut self.state {
-            panic!();
+    pub fn init(&mut self, mark:&LetterMark, con_count:usize, ang:f64, word: &GallWord, config:&Config) -> usize {
+        if self.state.is_some() {
+            panic!("Tainer is already initialised");
         } else {
             let state = TainerState::new(
                 con_count as f64 * ang,
                 mark,
                 word,
+                config,
             );
             self.state = Some(state);
         };
         con_count + 1
     }
+    pub fn counts(&self) -> ShapeCounts {
+        [self.stem.len(), self.vowel.len(), self.dot.len(), self.node.len(), self.line.len()]
+    }
+    //Everything added since `before` belongs to the letter `id`
+    pub fn tag_letter(&mut self, id:String, text:String, class:String, before:ShapeCounts) {
+        let [stem, vowel, dot, node, line] = before;
+        for dash in &mut self.node[node..] {
+            dash.letter = id.clone();
+        }
+        self.letters.push(LetterSpan {
+            id,
+            text,
+            class,
+            stem: stem..self.stem.len(),
+            vowel: vowel..self.vowel.len(),
+            dot: dot..self.dot.len(),
+            node: node..self.node.len(),
+            line: line..self.line.len(),
+        });
+    }
     pub fn is_stateless(&self) -> bool {
         self.state.is_none()
     }
@@ -116,58 +170,73 @@ impl GallTainer {
             None
         }
     }
+    pub fn is_number(&self) -> bool {
+        self.get_state().is_ok_and(|state| state.number)
+    }
     pub fn is_empty(&self) -> bool {
-        self.stem.is_empty() && self.vowel.is_empty() && self.mark.is_empty()
+        self.stem.is_empty() && self.vowel.is_empty()
     }
-    pub fn populate(&mut self, l_mark: LetterMark, d_mark:(Option<Decor>, i8), repeat:u8, word: &GallWord) {
+    pub fn populate(&mut self, l_mark: LetterMark, d_mark:(Option<Decor>, i8), repeat:u8, word: &GallWord, config:&Config) -> Result<(), Error> {
+        let vowel_dash = match l_mark {
+            LetterMark::GallVowel(VowelType::I) => Some(NodeKind::VowelIn),
+            LetterMark::GallVowel(VowelType::U) => Some(NodeKind::VowelOut),
+            _ => None,
+        };
         match l_mark {
             LetterMark::Stem(stem) => {
                 for n in 0..=repeat {
-                    self.add_stem(stem, word, n);
+                    self.add_stem(stem, word, n, config);
                 };                
             },
+            LetterMark::GallVowel(VowelType::O1) => self.populate_o1(repeat, word, config),
             LetterMark::GallVowel(vow) => {
                 for n in 0..=repeat {
-                    self.add_vowel(vow,word, n);
-                };
-            },
-            LetterMark::Digit(mut num) => {
-                self.add_digit(word);
-                println!("{}",num);
-                if num.is_negative() {
-                    //add negative mark
-                    num = num.abs();
-                };
-                if num >= 5 {
-                    self.add_dot(1, word.get_radius());
-                    num -= 5;
+                    self.add_vowel(vow,word, n, config);
                 };
-                for _ in 0..=num {
-                    //add marks
-                }
             },
-            LetterMark::GallMark => {},//todo!(),
+            LetterMark::Number(num) => self.add_number(&num, word, config)?,
+            LetterMark::GallMark => unreachable!("GallWord::populate skips punctuation, it is drawn on the sentence"),
         }
         if let Some(dot) = d_mark.0 {
             if dot == Decor::Dot {
                 for n in 0..d_mark.1 {
-                    self.add_dot(n - 1, word.get_radius());
+                    _ = self.add_dot(n - 1, config);
                 }    
+            } else if let Some(kind) = vowel_dash {
+                self.add_vowel_dash(kind, word)?;
             } else {
                 for n in 0..d_mark.1 {
-                    self.add_dash(n - 1, word.get_radius());
+                    _ = self.add_dash(n - 1, word.get_radius(), config);
                 }
             }
         }
+        Ok(())
     }
-    // pub fn populate_o1(&mut self, repeat:bool, word: &GallWord) {
-    //     let letter = self.create_vowel(VowelType::O1,word);
-    //     if repeat {
-    //         self.add_vowel(self.create_vowel(VowelType::O1,word));
-    //     };
-    //     self.add_vowel(letter);
-    // }
-    fn init_state_vow(&mut self, vow:VowelType, word: &GallWord) -> GallLoc {
+    //O after a consonant: rings on the consonant's edge facing the word centre,
+    //repeats (OO, OOO) drawn concentric
+    pub fn populate_o1(&mut self, repeat:u8, word: &GallWord, config:&Config) {
+        for n in 0..=repeat {
+            self.add_o1(word, n, config);
+        }
+    }
+    fn add_o1(&mut self, word: &GallWord, repeat:u8, config:&Config) {
+        let rank = self.vowel.len();
+        let thick = word.thick()*config.vowel_thick_frac + f64::from(rank as u8 - repeat) * config.consec_lett_growth;
+        let radius = word.radius()*config.vowel_frac_of_wrd + f64::from(repeat)*(config.stack_sep_dist + 2.0*thick);
+        let stem = self.stem.last().expect("attached O needs a stem");
+        let loc = GallLoc::new(
+            self.ang(),
+            0.0,
+            stem.pos_ref(),
+        );
+        let mut vowel = GallVowel::new(loc, Rc::new(Cell::new(radius)), thick, VowelType::O1, stem);
+        vowel.o_attach_init(stem);
+        if let Some(state) = &mut self.state {
+            state.vowel = true;
+        }
+        self.vowel.push(vowel);
+    }
+    fn init_state_vow(&mut self, vow:VowelType, word: &GallWord, config:&Config) -> GallLoc {
         let mut loc = GallLoc::new(
             self.ang(),
             1.0,
@@ -182,31 +251,30 @@ impl GallTainer {
                 _ => state.letter_dist.get(),
             };
             _ = loc.mut_dist(dist);
-            let rad = word.radius()*Config::VOWEL_FRAC_OF_WRD;
+            let rad = word.radius()*config.vowel_frac_of_wrd;
             state.letter_rad = Rc::new(Cell::new(rad));
             state.vowel = true;
         }
         loc
     }
-    pub fn add_dot(&mut self, num: i8, w_rad: Rc<Cell<f64>>) -> Result<(), Error>{
+    pub fn add_dot(&mut self, num: i8, config:&Config) -> Result<(), Error>{
         self.dot.push(Dot::new(
             GallRelLoc::new(
                 self.get_ang(),
-                PI + Config::DEF_DOT_SPREAD * f64::from(num),
+                PI + config.def_dot_spread * f64::from(num),
                 self.get_state()?.letter_rad.clone(),
                 0.0,
                 self.get_state()?.letter_pos.clone(),
             ),
-            Config::DOT_RADIUS,
-            w_rad,     
+            config.dot_radius,
         ));
         Ok(())
     }
-    pub fn add_dash(&mut self, num: i8, w_rad: Rc<Cell<f64>>) -> Result<(), Error> {
+    pub fn add_dash(&mut self, num: i8, w_rad: Rc<Cell<f64>>, config:&Config) -> Result<(), Error> {
         self.node.push(GallNode::new(
             GallRelLoc::new(
                 self.get_ang(),
-                PI + num as f64 * Config::DEF_DOT_SPREAD,
+                PI + num as f64 * config.def_dot_spread,
                 self.get_state()?.letter_rad.clone(),
                 0.0,
                 self.get_state()?.letter_pos.clone(),
@@ -216,13 +284,32 @@ impl GallTainer {
         ));
         Ok(())
     }
-    pub fn add_stem(&mut self, stem: StemType, word: &GallWord, repeat: u8) {
+    //I and U dashes sit on the (outermost) vowel ring, on the side they point to
+    pub fn add_vowel_dash(&mut self, kind:NodeKind, word: &GallWord) -> Result<(), Error> {
+        let vowel = self.vowel.last().ok_or(Error::new(GallError::NoVowelInTainer))?;
+        let offset = if kind == NodeKind::VowelIn {PI} else {0.0};
+        self.node.push(GallNode::vowel(
+            GallRelLoc::new(
+                self.get_ang(),
+                offset,
+                vowel.get_radius(),
+                0.0,
+                vowel.pos_ref(),
+            ),
+            self.get_state()?.letter_dist.clone(),
+            word.get_radius(),
+            kind,
+            word.pos_ref(),
+        ));
+        Ok(())
+    }
+    pub fn add_stem(&mut self, stem: StemType, word: &GallWord, repeat: u8, config:&Config) {
         let rank = self.stem.len();
-        let thick = word.thick()*Config::LETTER_THICK_FRAC + f64::from(rank as u8 - repeat) * Config::CONSEC_LETT_GROWTH;
+        let thick = word.thick()*config.letter_thick_frac + f64::from(rank as u8 - repeat) * config.consec_lett_growth;
         let ang = self.ang();
         if let Some(state) = &mut self.state {
             let rad = state.letter_rad.clone();
-            let new_rad = rad.get() + Config::STACK_SEP_DIST+2.0*thick;
+            let new_rad = rad.get() + config.stack_sep_dist+2.0*thick;
             state.letter_rad = Rc::new(Cell::new(new_rad));
             self.stem.push(Stem::new(
                 GallLoc::new(
@@ -237,16 +324,16 @@ impl GallTainer {
             ));
         };
     }
-    pub fn add_vowel(&mut self, vow:VowelType, word: &GallWord, repeat:u8) {
+    pub fn add_vowel(&mut self, vow:VowelType, word: &GallWord, repeat:u8, config:&Config) {
         let rank = self.vowel.len();
-        let thick = word.thick()*Config::VOWEL_THICK_FRAC + f64::from(rank as u8 - repeat) * Config::CONSEC_LETT_GROWTH;
-        let loc = if rank <= 0 {
-            self.init_state_vow(vow, word)
+        let thick = word.thick()*config.vowel_thick_frac + f64::from(rank as u8 - repeat) * config.consec_lett_growth;
+        let loc = if rank == 0 {
+            self.init_state_vow(vow, word, config)
         } else {
             let ang = self.ang();
             let state = self.get_mut_state().expect("existing vowels should set state");
             let rad = state.letter_rad.clone();
-            let new_rad = rad.get() + Config::STACK_SEP_DIST+2.0*thick;
+            let new_rad = rad.get() + config.stack_sep_dist+2.0*thick;
             state.letter_rad = Rc::new(Cell::new(new_rad));
             GallLoc::new(
                 ang,
@@ -254,48 +341,103 @@ impl GallTainer {
                 word.pos_ref(),
             )
         };
-        // if let Some(stem) = self.state.stem_type.get() {
-        //     match stem {
-        //         // StemType::J|StemType::B => match vow {
-        //         //     VowelType::E|VowelType::I|VowelType::U => {
-        //         //         new_vowel.center_on_stem(&self.stem[0]);
-        //         //     },
-        //         //     VowelType::O1 => {
-        //         //         new_vowel.o_attach_init(&self.stem[0]);
-        //         //     },
-        //         //     _ => {},
-        //         // },
-        //         _ => {},
-        //     }
-        // }
-        self.vowel.push(GallVowel::new(
+        let mut new_vowel = GallVowel::new(
             loc,
             self.get_state().unwrap().letter_rad.clone(),
             thick,
             vow,
             word
-        ))
+        );
+        if let (Some(stem_type), Some(stem)) = (self.stem_type(), self.stem.first()) {
+            //vowel following a consonant, placed relative to it
+            match (stem_type, vow) {
+                (StemType::J|StemType::B|StemType::Z, VowelType::E|VowelType::I|VowelType::U) => {
+                    new_vowel.center_on_stem(stem);
+                },
+                //the S stem centre is off the word, so these sit on the word line instead
+                (StemType::S, VowelType::E|VowelType::I|VowelType::U) => {
+                    _ = new_vowel.mut_dist(word.radius());
+                },
+                (_, VowelType::A) => {
+                    let clear = word.outer_radius() + new_vowel.outer_radius();
+                    _ = new_vowel.mut_dist(clear.max(word.radius()*1.2));
+                },
+                (_, VowelType::O1|VowelType::O2) => {},
+            }
+        }
+        self.vowel.push(new_vowel)
     }
-    pub fn add_digit(&mut self, word: &GallWord) {
-        let rank = self.stem.len();
-        let thick = word.thick()*Config::DIGIT_THICK_FRAC;
+    //Digits are concentric rings, the first digit innermost, inside a number
+    //circle drawn like a letter. A digit ring carries a dot for 5 and a line for
+    //each 1 left over, so 0 is a bare ring. A decimal point is a dot between the
+    //rings either side of it, a group separator a line across that gap, and a
+    //negative number has a line through its circle pointing out of the word.
+    pub fn add_number(&mut self, num:&GallNumber, word: &GallWord, config:&Config) -> Result<(), Error> {
+        let thick = word.thick()*config.digit_thick_frac;
+        let gap = config.num_sep_dist + 2.0*thick;
+        let core = self.get_state()?.letter_rad.get();
+        let outer = core + gap*num.digits.len() as f64;
+        //keep the number circle inside the word
+        let room = word.inner_radius() - outer - word.thick()*config.letter_thick_frac - config.num_sep_dist;
+        let state = self.get_mut_state()?;
+        state.letter_dist.set(state.letter_dist.get().min(room).max(0.0));
+        for (place, &digit) in num.digits.iter().enumerate() {
+            let rad = core + gap*place as f64;
+            let ring = self.add_digit(rad, thick, word)?;
+            if num.point == Some(place) {
+                let dot_rad = (config.num_sep_dist/2.0).min(config.dot_radius);
+                self.add_number_dot(&ring, rad - gap/2.0, dot_rad, PI/2.0);
+            }
+            if num.groups.contains(&place) {
+                self.add_digit_line(&ring, rad - gap/2.0, gap - 2.0*thick, thick, PI/2.0);
+            }
+            if digit >= 5 {
+                self.add_number_dot(&ring, rad, thick*1.5, 0.0);
+            }
+            let lines = digit % 5;
+            for n in 0..lines {
+                let offset = f64::from(n) - f64::from(lines - 1)/2.0;
+                self.add_digit_line(&ring, rad, gap*0.8, thick/2.0, PI + offset*config.def_dot_spread/2.0);
+            }
+        }
+        let circle = self.add_digit(outer, word.thick()*config.letter_thick_frac, word)?;
+        if num.negative {
+            self.add_digit_line(&circle, outer, gap*1.5, thick/2.0, 0.0);
+        }
+        Ok(())
+    }
+    //Returns the ring's centre, for the marks drawn on it
+    fn add_digit(&mut self, rad:f64, thick:f64, word: &GallWord) -> Result<Rc<Cell<(f64,f64)>>, Error> {
         let ang = self.ang();
-        if let Some(state) = &mut self.state {
-            let rad = state.letter_rad.clone();
-            let new_rad = rad.get() + Config::NUM_SEP_DIST+2.0*thick;
-            state.letter_rad = Rc::new(Cell::new(new_rad));
-            self.stem.push(Stem::new(
-                GallLoc::new(
-                    ang,
-                    state.letter_dist.get(),
-                    word.pos_ref(),
-                ),
-                state.letter_rad.clone(),
-                thick,
-                StemType::J,
-                word,
-            ));
-        };
+        let state = self.get_mut_state()?;
+        state.letter_rad = Rc::new(Cell::new(rad));
+        let stem = Stem::new(
+            GallLoc::new(
+                ang,
+                state.letter_dist.get(),
+                word.pos_ref(),
+            ),
+            state.letter_rad.clone(),
+            thick,
+            StemType::J,
+            word,
+        );
+        let center = stem.pos_ref();
+        self.stem.push(stem);
+        Ok(center)
+    }
+    fn add_number_dot(&mut self, ring:&Rc<Cell<(f64,f64)>>, dist:f64, radius:f64, offset:f64) {
+        self.dot.push(Dot::new(
+            GallRelLoc::new(self.get_ang(), offset, Rc::new(Cell::new(dist)), 0.0, ring.clone()),
+            radius,
+        ));
+    }
+    fn add_digit_line(&mut self, ring:&Rc<Cell<(f64,f64)>>, dist:f64, length:f64, thick:f64, offset:f64) {
+        self.line.push(DigitLine::new(
+            GallRelLoc::new(self.get_ang(), offset, Rc::new(Cell::new(dist)), 0.0, ring.clone()),
+            length,
+            thick,
+        ));
     }
     pub fn thi_calc(&self) -> Result<(f64,f64), Error> {
         let (stem1,stem2) = self.stack_check()?;
@@ -306,6 +448,36 @@ impl GallTainer {
         let (stem1,stem2) = self.stack_check()?;
         Ok((stem1.inner_theta()?,stem2.outer_theta()?))
     }
+    //Every angle the skeleton and stack gaps are drawn with, so a stem that
+    //misses the word line shows up before rendering
+    pub fn skel_check(&self) -> Result<(), Error> {
+        match self.stem_type() {
+            Some(StemType::B) => {
+                self.thi_calc()?;
+                self.theta_calc()?;
+                for stem in self.stem.iter().skip(1) {
+                    stem.outer_thi()?;
+                    stem.outer_thi2()?;
+                    stem.outer_theta()?;
+                    stem.outer_theta2()?;
+                }
+            },
+            Some(StemType::S) => {
+                self.thi_calc()?;
+                self.theta_calc()?;
+                for (n, stem) in self.stem.iter().enumerate() {
+                    stem.inner_thi2()?;
+                    stem.inner_theta2()?;
+                    if n > 0 {
+                        stem.inner_thi()?;
+                        stem.inner_theta()?;
+                    }
+                }
+            },
+            _ => {},
+        }
+        Ok(())
+    }
     pub fn stack_check(&self) -> Result<(&Stem, &Stem), Error> {
         if let (Some(stem1), Some(stem2)) = (self.stem.first(), self.stem.last()) {
             if self.stem_type() == Some(&StemType::B) {
@@ -378,6 +550,9 @@ impl GallTainer {
         for dot in &mut self.dot {
             dot.update()
         }
+        for line in &mut self.line {
+            line.update()
+        }
         Ok(())
     }
     pub fn get_state(&self) -> Result<&TainerState, Error> {
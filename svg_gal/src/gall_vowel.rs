@@ -2,14 +2,39 @@ use std::cell::Cell;
 use std::f64::consts::PI;
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+
 use crate::gall_errors::{Error, GallError};
 use crate::gall_circle::{ChildCircle, Circle, HollowCircle};
 use crate::gall_loc::{GallLoc, Location};
 use crate::gall_ord::PolarOrdinate;
 use crate::gall_stem::Stem;
-//O1 is on a letter, O2 is on a word
-#[derive(PartialEq, Clone, Copy)]
-pub enum VowelType {A,E,I,O1,O2,U}
+//O1 is on a letter, O2 is on a word. Orthography tables only name "o",
+//which becomes O1 once it follows a consonant.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VowelType {
+    A,
+    E,
+    I,
+    #[serde(skip_deserializing)]
+    O1,
+    #[serde(rename = "o")]
+    O2,
+    U,
+}
+
+impl VowelType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VowelType::A => "a",
+            VowelType::E => "e",
+            VowelType::I => "i",
+            VowelType::O1|VowelType::O2 => "o",
+            VowelType::U => "u",
+        }
+    }
+}
 
 pub struct GallVowel {
     loc: GallLoc,
@@ -22,7 +47,6 @@ pub struct GallVowel {
 
 impl GallVowel {
     pub fn new<T:HollowCircle>(loc:GallLoc, radius: Rc<Cell<f64>>, thickness:f64, vowel_type: VowelType, parent:&T) -> GallVowel {
-        let radius = radius;
         let thickness = Rc::new(Cell::new(thickness));
         let parent_radius = parent.get_radius().clone();
         let parent_thickness = parent.get_thickness().clone();
@@ -39,10 +63,11 @@ impl GallVowel {
         self.set_center(stem.pos_ref());
         _ = self.mut_dist(0.0);
     }
+    //Sits on the stem's edge, on the side facing the word centre
     pub fn o_attach_init(&mut self, stem:&Stem) {
-        self.set_center(stem.get_center());
+        self.set_center(stem.pos_ref());
+        self.mut_ang(stem.ang().unwrap_or(0.0) + PI);
         _ = self.mut_dist(stem.radius());
-        _ = self.mut_ccw(PI);
     }
 }
 impl Location for GallVowel {
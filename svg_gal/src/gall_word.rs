@@ -6,88 +6,99 @@ use std::rc::Rc;
 
 use crate::gall_circle::{Circle, HollowCircle};
 use crate::gall_config::Config;
-use crate::gall_errors::{Error, GallError};
+use crate::gall_errors::Error;
 use crate::gall_fn::{self, LetterMark, ProcessedWord};
-use crate::gall_loc::{GallLoc, LocHolder};
+use crate::gall_loc::{GallLoc, LocHolder, Location};
+use crate::gall_meta;
 use crate::gall_node::GallNode;
 use crate::gall_ord::{GallOrd, OrdHolder};
-use crate::gall_stem::StemType;
 use crate::gall_tainer::GallTainer;
 use crate::gall_vowel::VowelType;
 
+const MAX_SPREAD: usize = 10_000;
+
 pub struct GallWord {
     loc: GallLoc,
+    pub id: String,
+    pub text: String,
     pub tainer_vec: Vec<GallTainer>,
     radius: Rc<Cell<f64>>,
     thickness: Rc<Cell<f64>>,
 }
 
 impl GallWord {
-    pub fn new(processed_word:ProcessedWord, loc:GallLoc, radius: f64, thick:f64) -> GallWord {
+    pub fn new(processed_word:ProcessedWord, id:String, loc:GallLoc, radius: f64, thick:f64, config:&Config) -> Result<GallWord, Error> {
         let tainer_vec = Vec::with_capacity(processed_word.length);
         let mut word = GallWord{
             loc,
+            id,
+            text: processed_word.word.clone(),
             tainer_vec,
             radius: Rc::new(Cell::new(radius)),
             thickness: Rc::new(Cell::new(thick))
         };
-        word.populate(processed_word);
-        word
+        word.populate(processed_word, config)?;
+        Ok(word)
     } 
-    fn populate(&mut self, mut processed_word:ProcessedWord) {
-        let word = processed_word.word;
+    fn populate(&mut self, processed_word:ProcessedWord, config:&Config) -> Result<(), Error> {
         let tainer_ang = TAU/(processed_word.length as f64); 
+        let stack = config.stack && processed_word.stack;
         let mut con_count:usize = 0;
         let mut con = GallTainer::new(); // create new container
-        for cha in word.chars() {
-            let (mut l_mark, repeats) = gall_fn::stem_lookup(&cha);
-            let d_mark = gall_fn::dot_lookup(&cha);
+        for (index, token) in processed_word.tokens.iter().enumerate() {
+            let (mut l_mark, repeats) = gall_fn::stem_lookup(token);
+            if l_mark == LetterMark::GallMark {
+                continue;
+            }
+            let class = gall_meta::letter_class(&l_mark);
+            let d_mark = gall_fn::dot_lookup(token);
+            let mut attach_o = false;
             //check if we can add to container
             if con.is_stateless() {
-                if let LetterMark::Digit(num) = l_mark {
-                    if let Some(neg) = processed_word.neg_digit.pop() {
-                        l_mark = LetterMark::Digit(if neg {-num}else{num});
-                    }
-                }
-                con_count = con.init(&l_mark,con_count,tainer_ang, self);
+                con_count = con.init(&l_mark,con_count,tainer_ang, self, config);
+            } else if con.is_number() || matches!(l_mark, LetterMark::Number(_)) {
+                //numbers always stand alone
+                self.tainer_vec.push(con);
+                con = GallTainer::new();
+                con_count = con.init(&l_mark,con_count,tainer_ang, self, config);
             } else {
                 match &l_mark {
                     LetterMark::Stem(stem) => {
-                        if (!Config::STACK && !con.is_empty()) || 
+                        if (!stack && !con.is_empty()) || 
                         (Some(stem) != con.stem_type()) || (!con.vowel.is_empty()) {
                             self.tainer_vec.push(con);
                             con = GallTainer::new();
-                            con_count = con.init(&l_mark,con_count,tainer_ang, self);
+                            con_count = con.init(&l_mark,con_count,tainer_ang, self, config);
                         }
                     },
                     LetterMark::GallVowel(vow) => {
-                        if !Config::STACK && !con.vowel.is_empty() {
+                        let other_vowel = con.vowel.last().is_some_and(|last| last.vowel_type != *vow);
+                        if (!stack && !con.vowel.is_empty()) || other_vowel {
                             self.tainer_vec.push(con);
                             con = GallTainer::new();
-                            con_count = con.init(&l_mark,con_count,tainer_ang, self);
-                        } else if !con.stem.is_empty() && vow == &VowelType::O2{
-                            //con.populate_o1(repeat, &self);
-                            continue;
-                        }
-                    },
-                    LetterMark::Digit(num) => {
-                        if con.stem_type() != Some(&StemType::J) || con.mark.is_empty() {
-                            con = GallTainer::new();
-                            con_count = con.init(&l_mark,con_count,tainer_ang, self);
-                            if let Some(neg) = processed_word.neg_digit.pop() {
-                                l_mark = LetterMark::Digit(if neg {-num}else{*num});
-                            }
+                            con_count = con.init(&l_mark,con_count,tainer_ang, self, config);
+                        } else if vow == &VowelType::O2 && !con.stem.is_empty() && con.vowel.is_empty() {
+                            //O straight after a consonant sits on that consonant
+                            attach_o = true;
                         }
                     },
-                    LetterMark::GallMark => {},
+                    LetterMark::Number(_)|LetterMark::GallMark => {},
                 }
             }
+            if attach_o {
+                l_mark = LetterMark::GallVowel(VowelType::O1);
+            }
             //actually add to the container
-            con.populate(l_mark, d_mark, repeats, &self)
+            let before = con.counts();
+            con.populate(l_mark, d_mark, repeats, self, config)?;
+            con.tag_letter(gall_meta::letter_id(&self.id, index), gall_meta::token_text(token), class, before);
+        }
+        if !con.is_stateless() {
+            self.tainer_vec.push(con);
         }
-        self.tainer_vec.push(con);
+        Ok(())
     }
-    fn check_radius(&self, new_radius:f64) -> Result<(),Error> {
+    fn check_radius(&self, _new_radius:f64) -> Result<(),Error> {
         //todo!();
         Ok(())
     }
@@ -108,7 +119,7 @@ impl GallWord {
         let mut edge_iter = self.gen_edge_vec()?.into_iter();
         let mut dists = Vec::with_capacity(self.tainer_vec.len());
         if let Some((first, mut current_edge)) = edge_iter.next() {
-            while let Some((cw_edge,ccw_edge)) = edge_iter.next() {
+            for (cw_edge,ccw_edge) in edge_iter {
                 dists.push(cw_edge - current_edge);
                 current_edge = ccw_edge;
             }
@@ -125,7 +136,7 @@ impl GallWord {
         let mut loop_iter = zip(dists, &mut self.tainer_vec);
         let (first, con1) = loop_iter.next().expect("empty?");
         let mut left = first;
-        while let Some((right, con)) = loop_iter.next() {
+        for (right, con) in loop_iter {
             let movement = (right - left)/3.0;
             _ = con.bound_ccw_rotate(movement);
             if movement > change {
@@ -144,8 +155,14 @@ impl GallWord {
             None
         }
     }
+    //Letters too wide to all fit round the word push each other back and
+    //forth for ever, so they stop after MAX_SPREAD rounds
     pub fn spread(&mut self) {
-        while let Some(_) = self.even_tainer_spread() {};
+        for _ in 0..MAX_SPREAD {
+            if self.even_tainer_spread().is_none() {
+                break;
+            }
+        }
     }
     pub fn collect_nodes(&mut self) -> Vec<&mut GallNode> {
         let mut nodes = Vec::new();
@@ -155,6 +172,23 @@ impl GallWord {
         }
         nodes
     }
+    //How far the word reaches from its centre, counting letters, vowels
+    //and dots that stick out past the word circle
+    pub fn extent(&self) -> f64 {
+        let (x, y) = self.svg_ord();
+        let reach = |pos:(f64,f64), radius:f64| (pos.0-x).hypot(pos.1-y) + radius;
+        let mut extent = self.outer_radius();
+        for con in &self.tainer_vec {
+            let stems = con.stem.iter().map(|stem| reach(stem.svg_ord(), stem.outer_radius()));
+            let vowels = con.vowel.iter().map(|vow| reach(vow.svg_ord(), vow.outer_radius()));
+            let dots = con.dot.iter().map(|dot| reach(dot.svg_ord(), dot.radius()));
+            extent = stems.chain(vowels).chain(dots).fold(extent, f64::max);
+        }
+        extent
+    }
+    pub fn skel_check(&self) -> Result<(), Error> {
+        self.tainer_vec.iter().try_for_each(GallTainer::skel_check)
+    }
     pub fn basic(&mut self) {
         for con in &mut self.tainer_vec {
             con.stem_sort();
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use crate::gall_config::Config;
+use crate::plot::{self, Point, Segment, Sheet, Stroke};
+use crate::render::ViewBox;
+
+//Arcs shorter than this (in mm) are left out, as a G2/G3 that ends where it
+//starts is a full circle to most controllers
+const MIN_ARC: f64 = 0.001;
+
+//Absolute millimetre G-code for a pen plotter. Each pen path is one pen down,
+//with arcs kept as G2/G3 and curved connectors broken into short lines.
+pub fn write(strokes:Vec<Stroke>, view:ViewBox, phrase:&str, seed:u64, config:&Config) -> String {
+    let sheet = Sheet::new(view, config);
+    let paths = plot::pen_paths(strokes, sheet.home(), config.pen_width/2.0);
+    let mut code = String::new();
+    let comment:String = phrase.chars().filter(|cha| !matches!(cha, '('|')')).collect();
+    let _ = writeln!(code, "(svg_gal: {}, seed {})", comment, seed);
+    code.push_str("G21\nG90\n");
+    let _ = writeln!(code, "{}", config.gcode_pen_up);
+    for path in &paths {
+        let (x, y) = sheet.place(path.start);
+        let _ = if config.gcode_travel_feed > 0.0 {
+            writeln!(code, "G1 X{:.3} Y{:.3} F{}", x, y, config.gcode_travel_feed)
+        } else {
+            writeln!(code, "G0 X{:.3} Y{:.3}", x, y)
+        };
+        let _ = writeln!(code, "{}", config.gcode_pen_down);
+        let mut feed = format!(" F{}", config.gcode_feed);
+        let mut pen = path.start;
+        for segment in &path.segments {
+            for line in moves(&sheet, pen, segment) {
+                let _ = writeln!(code, "{}{}", line, feed);
+                feed.clear();
+            }
+            pen = segment.end();
+        }
+        let _ = writeln!(code, "{}", config.gcode_pen_up);
+    }
+    code.push_str("G0 X0 Y0\nM2\n");
+    code
+}
+
+//The G1, G2 or G3 moves drawing `segment` from `pen`
+fn moves(sheet:&Sheet, pen:Point, segment:&Segment) -> Vec<String> {
+    let line = |point:Point| {
+        let (x, y) = sheet.place(point);
+        format!("G1 X{:.3} Y{:.3}", x, y)
+    };
+    match *segment {
+        Segment::Line(end) => vec![line(end)],
+        Segment::Arc{centre, end, clockwise} => {
+            let (start, centre, end) = (sheet.place(pen), sheet.place(centre), sheet.place(end));
+            if plot::dist(start, end) < MIN_ARC {
+                return Vec::new()
+            }
+            let code = if sheet.clockwise(clockwise) {"G2"} else {"G3"};
+            vec![format!("{} X{:.3} Y{:.3} I{:.3} J{:.3}", code, end.0, end.1, centre.0 - start.0, centre.1 - start.1)]
+        },
+        Segment::Quad(control, end) => plot::quad_lines(pen, control, end, sheet.scale).into_iter().map(line).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(words:&[&str], axis:char) -> Option<f64> {
+        words.iter().find_map(|word| word.strip_prefix(axis)?.parse().ok())
+    }
+
+    #[test]
+    fn arcs_and_pen_moves_agree() {
+        let config = Config::default();
+        let sent = crate::layout("the quick brown fox 42", &config).unwrap();
+        let (code, _) = crate::draw_gcode(sent, &config, 1);
+        let mut pen = (0.0, 0.0);
+        //the header lifts the pen first, so start as if it were down
+        let mut down = true;
+        let mut arcs = 0;
+        for line in code.lines() {
+            if line == config.gcode_pen_up || line == config.gcode_pen_down {
+                assert_eq!(down, line == config.gcode_pen_up, "pen not alternating at {}", line);
+                down = !down;
+                continue
+            }
+            let words:Vec<&str> = line.split_whitespace().collect();
+            let (Some(x), Some(y)) = (field(&words, 'X'), field(&words, 'Y')) else {continue};
+            if matches!(words[0], "G2"|"G3") {
+                assert!(down, "arc drawn with the pen up");
+                let centre = (pen.0 + field(&words, 'I').unwrap(), pen.1 + field(&words, 'J').unwrap());
+                let (start_rad, end_rad) = (plot::dist(pen, centre), plot::dist((x, y), centre));
+                assert!((start_rad - end_rad).abs() < 0.01, "{} starts at radius {} and ends at {}", line, start_rad, end_rad);
+                arcs += 1;
+            }
+            pen = (x, y);
+        }
+        assert!(!down, "pen left down");
+        assert!(arcs > 0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Sherman's Circular Gallifreyan generator.
+//!
+//! The quickest route is [`render_phrase`], which turns text into an
+//! [`svg::Document`]. The individual stages ([`layout`], [`draw`]) and the
+//! underlying modules are public for callers that need more control.
+
+use std::{cell::Cell, rc::Rc};
+
+use svg::Document;
+
+use crate::gall_circle::Circle;
+use crate::gall_loc::{GallLoc, Location};
+use crate::gall_pair::{GallLine, GallLinePair};
+
+pub mod gall_config;
+pub mod gcode;
+pub mod dxf;
+pub mod gall_fn;
+pub mod gall_lex;
+pub mod gall_meta;
+pub mod gall_orth;
+pub mod gall_phon;
+pub mod gall_errors;
+pub mod gall_ang;
+pub mod gall_ord;
+pub mod gall_loc;
+pub mod gall_node;
+pub mod gall_circle;
+pub mod gall_mark;
+pub mod gall_num;
+pub mod gall_stem;
+pub mod gall_vowel;
+pub mod gall_tainer;
+pub mod gall_word;
+pub mod gall_sentence;
+pub mod gall_pair;
+pub mod pairing;
+pub mod plot;
+pub mod raster;
+pub mod render;
+pub mod routing;
+
+pub use crate::gall_config::Config;
+pub use crate::gall_errors::{Error, GallError};
+pub use crate::gall_meta::decode;
+pub use crate::gall_sentence::GallSentence;
+pub use crate::pairing::PairScore;
+
+/// Parses a phrase and lays out its words inside a sentence circle.
+pub fn layout(phrase:&str, config:&Config) -> Result<GallSentence, Error> {
+    let word_list = gall_fn::phrase_parse(phrase, config)?;
+    if word_list.is_empty() {
+        //punctuation alone is dropped by the parse, but it was still a phrase
+        let err = if phrase.trim().is_empty() {GallError::EmptyPhrase} else {GallError::NoLetters};
+        return Err(Error::new(err));
+    }
+    let origin = Rc::new(Cell::new((config.width/2.0,config.height/2.0)));
+    let mut sent = GallSentence::new(
+        GallLoc::new(
+            0.0,
+            0.0,
+            origin,
+        ),
+        config.sent_radius,
+        config.sent_thick,
+    );
+    sent.phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    sent.generate(word_list, config)?;
+    sent.basic();
+    sent.skel_check()?;
+    Ok(sent)
+}
+
+/// Pairs up the dash nodes of a laid out sentence and renders everything.
+///
+/// The same `seed` always gives the same dash lines. It is recorded on the
+/// root element as `data-seed` so a render can be reproduced later.
+pub fn draw(sent:GallSentence, config:&Config, seed:u64) -> Result<Document, Error> {
+    Ok(draw_scored(sent, config, seed)?.0)
+}
+
+/// Same as [`draw`], also returning how well the dash lines could be paired.
+pub fn draw_scored(mut sent:GallSentence, config:&Config, seed:u64) -> Result<(Document, PairScore), Error> {
+    let view = render::view_box(&sent, config);
+    if config.line_art {
+        let (strokes, score) = plot_strokes(&mut sent, config, seed);
+        return Ok((render::render_line_art(&sent, strokes, view, config).set("data-seed", seed), score))
+    }
+    let phrase = sent.phrase.clone();
+    let (pairs, lines, score) = dash_lines(&mut sent, config, seed);
+    let (mut drawn, post_render) = render::render_init(pairs, lines, view, &phrase, config);
+    drawn = render::render_start(sent, drawn, config)?;
+    Ok((render::render_post(post_render, drawn).set("data-seed", seed), score))
+}
+
+/// Pairs up the dash nodes like [`draw`], then gives every line of the
+/// sentence as a single pen stroke.
+pub fn plot_strokes(sent:&mut GallSentence, config:&Config, seed:u64) -> (Vec<plot::Stroke>, PairScore) {
+    let (mut strokes, score) = connector_strokes(sent, config, seed);
+    strokes.extend(plot::sentence_strokes(sent, config));
+    (strokes, score)
+}
+
+/// Like [`plot_strokes`], but with the edges of the filled shapes in place of
+/// centre lines, for cutting. Dash lines stay centre lines.
+pub fn plot_outlines(sent:&mut GallSentence, config:&Config, seed:u64) -> (Vec<plot::Stroke>, PairScore) {
+    let (mut strokes, score) = connector_strokes(sent, config, seed);
+    strokes.extend(plot::sentence_outlines(sent));
+    (strokes, score)
+}
+
+fn connector_strokes(sent:&mut GallSentence, config:&Config, seed:u64) -> (Vec<plot::Stroke>, PairScore) {
+    let (pairs, lines, score) = dash_lines(sent, config, seed);
+    (plot::connector_strokes(&pairs, &lines), score)
+}
+
+//Pairs the dash nodes and runs the spares out to the sentence circle. Every
+//output goes through here, so a seed draws the same lines in each format.
+fn dash_lines<'a>(sent:&'a mut GallSentence, config:&Config, seed:u64) -> (Vec<GallLinePair<'a>>, Vec<GallLine<'a>>, PairScore) {
+    let mut rng = pairing::seeded_rng(seed);
+    let (ext_rad, ext_cent) = (sent.get_radius(),sent.get_center());
+    let obstacles = routing::Obstacles::from_sentence(sent);
+    let node_vec = sent.collect_nodes();
+    let (pairs, spares, score) = pairing::generate_pairs(node_vec, &obstacles, &mut rng, config);
+    let lines = pairing::extend_spares(spares, ext_rad,ext_cent, &obstacles, &mut rng, config);
+    (pairs, lines, score)
+}
+
+/// G-code for a pen plotter, sized by the `plot_*` and `gcode_*` settings.
+pub fn draw_gcode(mut sent:GallSentence, config:&Config, seed:u64) -> (String, PairScore) {
+    let view = render::view_box(&sent, config);
+    let (strokes, score) = plot_strokes(&mut sent, config, seed);
+    (gcode::write(strokes, view, &sent.phrase, seed, config), score)
+}
+
+/// A DXF for CAD and cutters, sized by the `plot_*` settings. Outlines unless
+/// `dxf_outlines` is off, then the line art centre lines.
+pub fn draw_dxf(mut sent:GallSentence, config:&Config, seed:u64) -> (String, PairScore) {
+    let view = render::view_box(&sent, config);
+    let (strokes, score) = if config.dxf_outlines {
+        plot_outlines(&mut sent, config, seed)
+    } else {
+        plot_strokes(&mut sent, config, seed)
+    };
+    (dxf::write(strokes, view, &sent.phrase, seed, config), score)
+}
+
+/// Text in, finished SVG document out. See [`pairing::random_seed`] for a fresh seed.
+pub fn render_phrase(phrase:&str, config:&Config, seed:u64) -> Result<Document, Error> {
+    draw(layout(phrase, config)?, config, seed)
+}
+
+/// Same as [`render_phrase`], serialised to an SVG string.
+pub fn render_svg_string(phrase:&str, config:&Config, seed:u64) -> Result<String, Error> {
+    Ok(render_phrase(phrase, config, seed)?.to_string())
+}
@@ -1,66 +1,112 @@
-use std::{cell::Cell, env, rc::Rc};
+use std::fs;
+use std::process::ExitCode;
 
-use crate::gall_circle::Circle;
-use crate::gall_config::Config;
-use crate::gall_loc::{GallLoc, Location};
-use crate::gall_sentence::GallSentence;
+use clap::Parser;
 
-mod gall_config;
-mod gall_fn;
-mod gall_errors;
-mod gall_ang;
-mod gall_ord;
-mod gall_loc;
-mod gall_node;
-mod gall_circle;
-mod gall_stem;
-mod gall_vowel;
-mod gall_tainer;
-mod gall_word;
-mod gall_sentence;
-mod gall_pair;
-mod pairing;
-mod render;
+use svg_gal::gall_meta::SentenceMeta;
+use svg_gal::{gall_fn, pairing, raster, render, Config};
 
-fn main() {
-    let ORIGIN = Rc::new(Cell::new((Config::WIDTH/2.0,Config::HEIGHT/2.0)));
-    println!("Initialising...");
-    let args = env::args();
-    let mut word_list = Vec::new();
-    let mut filename:String = "".to_string();
-    for raw_word in args {
-        if filename.len() == 0 {
-            filename += "SVGs\\"; //Save to SVGs folder
-            continue;//first argument is usually runpath
+use crate::cli::{Cli, Command, DecodeArgs, Format, RenderArgs};
+
+mod cli;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Render(args)) => run_render(&args),
+        Some(Command::Decode(args)) => run_decode(&args),
+        None => run_render(&cli.render),
+    };
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn run_render(args:&RenderArgs) -> Result<(), String> {
+    let log = args.verbosity();
+    log.info("Initialising...");
+    let raw_words = args.words().map_err(|e| format!("Could not read phrase from stdin: {}", e))?;
+    let mut config = Config::load(args.config.as_deref(), &args.overrides).map_err(|e| e.to_string())?;
+    if let Some(name) = &args.layout {
+        config.layout = name.parse().map_err(|e:svg_gal::Error| e.to_string())?;
+    }
+    if args.transparent {
+        config.enable_canvas = false;
+    }
+    if args.phonetic {
+        config.phonetic = true;
+    }
+    if args.line_art {
+        config.line_art = true;
+    }
+    let filename = args.output_path(&raw_words);
+    log.info("Generating...");
+    let mut sent = svg_gal::layout(&raw_words.join(" "), &config).map_err(|e| e.to_string())?;
+    log.debug(&format!("{} words, {} dash nodes", sent.words.len(), sent.collect_nodes().len()));
+    let view = render::view_box(&sent, &config);
+    //sized before drawing, so a size too large to export fails straight away
+    let pixels = match args.format {
+        Format::Png => Some(raster::pixel_size(view, args.size, args.dpi).map_err(|e| e.to_string())?),
+        Format::Svg if args.size.is_some() || args.dpi.is_some() => {
+            Some(raster::pixel_size(view, args.size, args.dpi).map_err(|e| e.to_string())?)
+        },
+        _ => None,
+    };
+    let seed = args.seed.unwrap_or_else(pairing::random_seed);
+    log.info(&format!("Rendering with seed {}...", seed));
+    if let Some(dir) = filename.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    }
+    let score = match args.format {
+        Format::Gcode|Format::Dxf => {
+            let (code, score) = if args.format == Format::Gcode {
+                svg_gal::draw_gcode(sent, &config, seed)
+            } else {
+                svg_gal::draw_dxf(sent, &config, seed)
+            };
+            log.info(&format!("Saving under {}", filename.display()));
+            fs::write(&filename, code).map_err(|e| format!("Could not write {}: {}", filename.display(), e))?;
+            score
+        },
+        Format::Svg => {
+            let (mut drawn, score) = svg_gal::draw_scored(sent, &config, seed).map_err(|e| e.to_string())?;
+            log.info(&format!("Saving under {}", filename.display()));
+            if let Some(size) = pixels {
+                drawn = drawn.set("width", size.0).set("height", size.1);
+            }
+            svg::save(&filename, &drawn).map_err(|e| e.to_string())?;
+            score
+        },
+        Format::Png => {
+            let (drawn, score) = svg_gal::draw_scored(sent, &config, seed).map_err(|e| e.to_string())?;
+            log.info(&format!("Saving under {}", filename.display()));
+            let size = pixels.expect("PNG is always sized");
+            raster::save_png(&filename, &drawn, size, seed).map_err(|e| e.to_string())?;
+            score
+        },
+    };
+    log.debug(&format!("Dash lines: {}", score));
+    log.info("Done!");
+    Ok(())
+}
+
+fn run_decode(args:&DecodeArgs) -> Result<(), String> {
+    let svg_text = args.read().map_err(|e| format!("Could not read {}: {}", args.file.display(), e))?;
+    let phrase = svg_gal::decode(&svg_text).map_err(|e| e.to_string())?;
+    println!("{}", phrase);
+    if let Some(expected) = &args.expect {
+        //compare what is drawn, so "Café" matches a file that says "cafe"
+        let config = Config::load(args.config.as_deref(), &args.overrides).map_err(|e| e.to_string())?;
+        let word_list = gall_fn::phrase_parse(expected, &config).map_err(|e| e.to_string())?;
+        let found = SentenceMeta::from_svg(&svg_text).map_err(|e| e.to_string())?;
+        let drawn = SentenceMeta::new(&word_list, &config).map_err(|e| e.to_string())?;
+        if found.words != drawn.words {
+            return Err(format!("The file does not say \"{}\"", expected));
         }
-        filename += &raw_word;
-        word_list.push(gall_fn::string_parse(raw_word));
-    }   
-    filename += ".svg";
-    println!("Generating...");
-    let mut sent = GallSentence::new(
-        GallLoc::new(
-            0.0,
-            0.0,
-            ORIGIN.clone(),
-        ),
-        Config::SENT_RADIUS,
-        Config::SENT_THICK,
-    );
-    sent.generate(word_list);
-    println!("Organizing...");
-    sent.basic();
-    let (ext_rad, ext_cent) = (sent.get_radius(),sent.get_center());
-    let node_vec = sent.collect_nodes();
-    let (pairs, spares) = pairing::generate_pairs(node_vec);
-    let lines = pairing::extend_spares(spares, ext_rad,ext_cent);
-    println!("Rendering...");
-    let (mut drawn, post_render) = render::render_init(pairs, lines);
-    drawn = render::render_start(sent, drawn);
-    drawn = render::render_post(post_render, drawn);
-    println!("Saving under {}", filename);
-    match svg::save(filename, &drawn) {
-        Ok(_) => println!("Done!"),
-        Err(message) => println!("{}", message),
     }
-}
\ No newline at end of file
+    Ok(())
+}
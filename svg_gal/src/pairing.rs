@@ -4,85 +4,584 @@ use std::cell::Cell;
 use std::f64::consts::{PI, TAU};
 use std::rc::Rc;
 
-use rand::rngs::ThreadRng;
-use rand::seq::SliceRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
+use crate::gall_ang;
+use crate::gall_config::Config;
 use crate::gall_loc::Location;
-use crate::gall_node::GallNode;
+use crate::gall_node::{GallNode, NodeKind};
 use crate::gall_ord::PolarOrdinate;
-use crate::gall_pair::{GallLine, GallLinePair};
+use crate::gall_pair::{self, GallLine, GallLinePair};
+use crate::routing::{self, ConnectorMode, Obstacles, Owner};
+
+//ChaCha is used over StdRng as its output is stable across rand releases,
+//so a seed keeps reproducing the same layout.
+pub type PairRng = ChaCha8Rng;
+
+pub fn seeded_rng(seed:u64) -> PairRng {
+    PairRng::seed_from_u64(seed)
+}
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
 
 fn align_nodes(node1:&mut GallNode, node2: &mut GallNode) {
-    let ang1 = node1.cent_ang2cent_ang(node2);
-    node1.mut_ang(ang1);
-    node2.mut_ang(ang1+PI);
-}
-
-fn unique_pair_test(node1:&mut GallNode, node2: &mut GallNode, pair_vec: &mut Vec<GallLinePair>) -> bool {
-    let center_ref1 = node1.get_center();
-    let center_ref2 = node2.get_center();
-    for pair in pair_vec {
-        let cent1 = pair.node1.get_center();
-        let cent2 = pair.node2.get_center();
-        if (center_ref1 == cent1 && center_ref2 == cent2) ||
-        (center_ref2 == cent1 && center_ref1 == cent2) {
+    let (start, end) = aligned_segment(node1, node2);
+    for (node, point) in [(node1, start), (node2, end)] {
+        let (x, y) = node.get_center().get();
+        node.mut_ang(gall_ang::svg_ang2gall_ang((point.1-y).atan2(point.0-x)));
+    }
+}
+
+//How the chosen pairing scored. Higher `pairs` always wins, then lower `cost`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PairScore {
+    pub pairs: usize,
+    pub spares: usize,
+    pub crossings: usize,
+    //lines through letters, vowels, dots or other words
+    pub obstructions: usize,
+    pub length: f64,
+    pub cost: f64,
+}
+
+impl std::fmt::Display for PairScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} pairs, {} spares, {} crossings, {} obstructions, length {:.1} (cost {:.1})",
+            self.pairs, self.spares, self.crossings, self.obstructions, self.length, self.cost)
+    }
+}
+
+type Segment = ((f64,f64),(f64,f64));
+
+//Restarts after the first (pure shortest-first) greedy start, jittered by the rng
+const RESTARTS: usize = 8;
+//Rounds of local search before a matching is kept as it is
+const MAX_PASSES: usize = 200;
+//Pairs checked for crossings over one whole solve, restarts included. Long
+//phrases spend it before the restarts run out. Counting work rather than time
+//keeps a seed's pairing the same on any machine.
+const WORK_BUDGET: usize = 50_000_000;
+//Re-pairing rounds after routing, each giving up the pairs no angle or bend cleared
+const REPAIR_ROUNDS: usize = 3;
+//What a pair that routing couldn't clear counts as when re-pairing, in obstructions
+const UNROUTED_HITS: usize = 10;
+//Node angle offsets tried when a pair is obstructed, in radians
+const REANGLE_STEPS: [f64; 7] = [0.0, 0.1, -0.1, 0.2, -0.2, 0.35, -0.35];
+//Angles tried around the node for a spare line
+const SPARE_STEPS: usize = 72;
+
+//Two nodes on the same letter pair may only be joined once
+fn unique_pair_test(letter1:usize, letter2:usize, chosen:&[(usize,usize)], letters:&[usize]) -> bool {
+    let key = (letter1.min(letter2), letter1.max(letter2));
+    !chosen.iter().any(|&(a,b)| (letters[a].min(letters[b]), letters[a].max(letters[b])) == key)
+}
+
+//Points where the line between two aligned nodes would be drawn, see `align_nodes`
+//Consonant dashes face the other end, vowel dashes stay on their own side
+fn aligned_segment(node1:&GallNode, node2:&GallNode) -> Segment {
+    let (fixed1, fixed2) = (node1.vowel_point(), node2.vowel_point());
+    let start = fixed1.unwrap_or_else(|| facing(node1, fixed2.unwrap_or(node2.get_center().get())));
+    let end = fixed2.unwrap_or_else(|| facing(node2, fixed1.unwrap_or(node1.get_center().get())));
+    (start, end)
+}
+
+fn facing(node:&GallNode, target:(f64,f64)) -> (f64,f64) {
+    let (x,y) = node.get_center().get();
+    let len = (target.0-x).hypot(target.1-y);
+    let (ux,uy) = if len > 0.0 {((target.0-x)/len,(target.1-y)/len)} else {(0.0,0.0)};
+    (x+node.dist()*ux,y+node.dist()*uy)
+}
+
+fn seg_length(seg:&Segment) -> f64 {
+    (seg.1.0-seg.0.0).hypot(seg.1.1-seg.0.1)
+}
+
+//Proper crossings only, lines meeting at a shared end don't count
+fn seg_cross(s1:&Segment, s2:&Segment) -> bool {
+    fn orient(a:(f64,f64), b:(f64,f64), c:(f64,f64)) -> f64 {
+        (b.0-a.0)*(c.1-a.1) - (b.1-a.1)*(c.0-a.0)
+    }
+    let (o1,o2) = (orient(s1.0,s1.1,s2.0),orient(s1.0,s1.1,s2.1));
+    let (o3,o4) = (orient(s2.0,s2.1,s1.0),orient(s2.0,s2.1,s1.1));
+    let eps = Config::COLLISION_DIST;
+    ((o1 > eps && o2 < -eps) || (o1 < -eps && o2 > eps)) &&
+    ((o3 > eps && o4 < -eps) || (o3 < -eps && o4 > eps))
+}
+
+//Candidate lines between every pair of nodes that pass each other's `node_test`,
+//evaluated on the nodes' starting angles like the drawing would.
+struct PairSolver {
+    letters: Vec<usize>,
+    //what each node's line may pass through, see `Obstacles::owner`
+    owners: Vec<Vec<Owner>>,
+    segs: Vec<Vec<Option<Segment>>>,
+    hits: Vec<Vec<usize>>,
+    cross_weight: f64,
+    obstacle_weight: f64,
+    //crossing checks `try_move` may still make in this `solve`
+    work_left: Cell<usize>,
+}
+
+//A matching under construction
+#[derive(Clone)]
+struct Matching {
+    mate: Vec<Option<usize>>,
+    chosen: Vec<(usize,usize)>,
+}
+
+impl PairSolver {
+    fn new(node_vec:&[&mut GallNode], obstacles:&Obstacles, config:&Config) -> PairSolver {
+        let mut centers:Vec<(f64,f64)> = Vec::new();
+        let mut letters = Vec::new();
+        for node in node_vec {
+            let center = node.get_center().get();
+            let letter = match centers.iter().position(|&c| c == center) {
+                Some(index) => index,
+                None => {centers.push(center); centers.len()-1},
+            };
+            letters.push(letter);
+        }
+        let size = node_vec.len();
+        let owners:Vec<Vec<Owner>> = node_vec.iter().map(|node| ends(obstacles, &[node])).collect();
+        let mut segs = vec![vec![None; size]; size];
+        let mut hits = vec![vec![0; size]; size];
+        let margin = f64::from(config.def_pair_thick);
+        for i in 0..size {
+            for j in i+1..size {
+                let (node1, node2) = (&*node_vec[i],&*node_vec[j]);
+                if node1.node_test(node2) && node2.node_test(node1) {
+                    let seg = aligned_segment(node1, node2);
+                    segs[i][j] = Some(seg);
+                    segs[j][i] = Some((seg.1,seg.0));
+                    hits[i][j] = obstacles.seg_hits(seg.0, seg.1, &[&owners[i][..], &owners[j]].concat(), margin);
+                    hits[j][i] = hits[i][j];
+                }
+            }
+        }
+        PairSolver {
+            letters,
+            owners,
+            segs,
+            hits,
+            cross_weight: config.pair_cross_weight,
+            obstacle_weight: config.pair_obstacle_weight,
+            work_left: Cell::new(WORK_BUDGET),
+        }
+    }
+    fn seg(&self, edge:(usize,usize)) -> Segment {
+        self.segs[edge.0][edge.1].expect("chosen pair must be a candidate")
+    }
+    fn score(&self, matching:&Matching) -> PairScore {
+        let mut crossings = 0;
+        let mut obstructions = 0;
+        let mut length = 0.0;
+        for (index, &edge) in matching.chosen.iter().enumerate() {
+            length += seg_length(&self.seg(edge));
+            obstructions += self.hits[edge.0][edge.1];
+            crossings += self.crossings(edge, &matching.chosen[index+1..]);
+        }
+        self.scored(matching, crossings, obstructions, length)
+    }
+    fn scored(&self, matching:&Matching, crossings:usize, obstructions:usize, length:f64) -> PairScore {
+        let pairs = matching.chosen.len();
+        PairScore {
+            pairs,
+            spares: matching.mate.len() - 2*pairs,
+            crossings,
+            obstructions,
+            length,
+            cost: length + self.cross_weight*crossings as f64 + self.obstacle_weight*obstructions as f64,
+        }
+    }
+    fn crossings(&self, edge:(usize,usize), others:&[(usize,usize)]) -> usize {
+        let seg = self.seg(edge);
+        others.iter().filter(|&&other| seg_cross(&seg, &self.seg(other))).count()
+    }
+    fn better(new:&PairScore, old:&PairScore) -> bool {
+        new.pairs > old.pairs ||
+        (new.pairs == old.pairs && new.cost < old.cost - Config::COLLISION_DIST)
+    }
+    //Applies the move if all added pairs are allowed and the score improves.
+    //Only the moved pairs' crossings change, so the score is updated by those
+    //rather than counted again over the whole matching.
+    fn try_move(&self, matching:&mut Matching, score:&mut PairScore, remove:&[(usize,usize)], add:&[(usize,usize)]) -> bool {
+        if remove.iter().any(|&(a,b)| matching.mate[a] != Some(b)) ||
+        add.iter().any(|&(a,b)| a == b || self.segs[a][b].is_none()) || self.work_left.get() == 0 {
             return false
         }
+        self.work_left.set(self.work_left.get().saturating_sub(matching.chosen.len() * (remove.len() + add.len())));
+        let mut trial = matching.clone();
+        let (mut crossings, mut obstructions, mut length) = (score.crossings, score.obstructions, score.length);
+        for &(a,b) in remove {
+            trial.chosen.retain(|&(c,d)| (c,d) != (a,b) && (c,d) != (b,a));
+            trial.mate[a] = None;
+            trial.mate[b] = None;
+        }
+        for (index, &edge) in remove.iter().enumerate() {
+            crossings -= self.crossings(edge, &trial.chosen) + self.crossings(edge, &remove[index+1..]);
+            obstructions -= self.hits[edge.0][edge.1];
+            length -= seg_length(&self.seg(edge));
+        }
+        for &(a,b) in add {
+            if trial.mate[a].is_some() || trial.mate[b].is_some() ||
+            !unique_pair_test(self.letters[a], self.letters[b], &trial.chosen, &self.letters) {
+                return false
+            }
+            crossings += self.crossings((a,b), &trial.chosen);
+            obstructions += self.hits[a][b];
+            length += seg_length(&self.seg((a,b)));
+            trial.mate[a] = Some(b);
+            trial.mate[b] = Some(a);
+            trial.chosen.push((a,b));
+        }
+        let new_score = self.scored(&trial, crossings, obstructions, length);
+        if PairSolver::better(&new_score, score) {
+            *matching = trial;
+            *score = new_score;
+            true
+        } else {false}
     }
-    true
+    fn greedy(&self, rng:Option<&mut PairRng>) -> Matching {
+        let size = self.letters.len();
+        let mut edges = Vec::new();
+        for i in 0..size {
+            for j in i+1..size {
+                if let Some(seg) = self.segs[i][j] {
+                    edges.push((seg_length(&seg),(i,j)));
+                }
+            }
+        }
+        if let Some(rng) = rng {
+            for edge in edges.iter_mut() {
+                edge.0 *= rng.gen_range(0.5..1.5);
+            }
+        }
+        edges.sort_by(|a,b| a.0.total_cmp(&b.0));
+        let mut matching = Matching { mate: vec![None; size], chosen: Vec::new() };
+        for (_, (a,b)) in edges {
+            if matching.mate[a].is_none() && matching.mate[b].is_none() &&
+            unique_pair_test(self.letters[a], self.letters[b], &matching.chosen, &self.letters) {
+                matching.mate[a] = Some(b);
+                matching.mate[b] = Some(a);
+                matching.chosen.push((a,b));
+            }
+        }
+        matching
+    }
+    //Local search: add, augment through a pair, re-pair to a spare, swap partners
+    fn improve(&self, matching:&mut Matching) -> PairScore {
+        let size = self.letters.len();
+        let mut score = self.score(matching);
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            let spares:Vec<usize> = (0..size).filter(|&i| matching.mate[i].is_none()).collect();
+            for (index, &u) in spares.iter().enumerate() {
+                for &w in &spares[index+1..] {
+                    improved |= self.try_move(matching, &mut score, &[], &[(u,w)]);
+                }
+            }
+            let spares:Vec<usize> = (0..size).filter(|&i| matching.mate[i].is_none()).collect();
+            for edge in matching.chosen.clone() {
+                let (a,b) = edge;
+                for &u in &spares {
+                    for &w in &spares {
+                        if u != w {
+                            improved |= self.try_move(matching, &mut score, &[edge], &[(u,a),(b,w)]);
+                        }
+                    }
+                }
+            }
+            for edge in matching.chosen.clone() {
+                let (a,b) = edge;
+                for u in 0..size {
+                    if matching.mate[u].is_some() {continue;}
+                    improved |= self.try_move(matching, &mut score, &[edge], &[(u,b)]) ||
+                    self.try_move(matching, &mut score, &[edge], &[(a,u)]);
+                }
+            }
+            let chosen = matching.chosen.clone();
+            for (index, &edge1) in chosen.iter().enumerate() {
+                for &edge2 in &chosen[index+1..] {
+                    let ((a,b),(c,d)) = (edge1,edge2);
+                    improved |= self.try_move(matching, &mut score, &[edge1,edge2], &[(a,c),(b,d)]) ||
+                    self.try_move(matching, &mut score, &[edge1,edge2], &[(a,d),(b,c)]);
+                }
+            }
+            if !improved || self.work_left.get() == 0 {break;}
+        }
+        //counted afresh so the reported length doesn't carry rounding from the moves
+        self.score(matching)
+    }
+    //Pairs that stayed obstructed after routing count as `UNROUTED_HITS`, and
+    //the local search runs again to pair their nodes elsewhere if it can
+    fn repair(&mut self, matching:&mut Matching, unrouted:&[(usize,usize)]) {
+        for &(a,b) in unrouted {
+            self.hits[a][b] = UNROUTED_HITS;
+            self.hits[b][a] = UNROUTED_HITS;
+        }
+        self.work_left.set(WORK_BUDGET);
+        self.improve(matching);
+    }
+    fn solve(&self, rng:&mut PairRng) -> (Matching, PairScore) {
+        self.work_left.set(WORK_BUDGET);
+        let mut best = self.greedy(None);
+        let mut best_score = self.improve(&mut best);
+        for _ in 0..RESTARTS {
+            let mut matching = self.greedy(Some(rng));
+            let score = self.improve(&mut matching);
+            if PairSolver::better(&score, &best_score) {
+                best = matching;
+                best_score = score;
+            }
+        }
+        (best, best_score)
+    }
+}
+
+fn ends(obstacles:&Obstacles, nodes:&[&GallNode]) -> Vec<Owner> {
+    nodes.iter().filter_map(|node| obstacles.owner(node)).collect()
+}
+
+fn node_pos(node:&GallNode) -> (f64,f64) {
+    (node.x(),node.y())
 }
 
-fn base_loop<'a>(mut spare_list:Vec<&'a mut GallNode>, pair_list:&mut Vec<GallLinePair<'a>>, rng:&mut ThreadRng) -> Vec<&'a mut GallNode> {
-    let mut new_spare = Vec::new();
-    spare_list.shuffle(rng);
-    let mut iter_loop = spare_list.into_iter();
-    let node0 = iter_loop.next().expect("empty node list");
-    new_spare.push(node0);
-    while let Some(node2) = iter_loop.next() {
-        let node1 = new_spare.pop().expect("Spare node empty?!?");
-        if node1.node_test(node2) && node2.node_test(node1) && 
-        unique_pair_test(node1, node2, pair_list) {
-            align_nodes(node1, node2);
-            pair_list.push(GallLinePair::new(node1,node2));
-            if let Some(buffer) = iter_loop.next() {
-                new_spare.push(buffer);
-            } else {break;}
-        } else {
-            new_spare.push(node1);
-            new_spare.push(node2);
+//Turns an obstructed pair's nodes away from the straight centre line, as far
+//as their `angle_test` allows, keeping the least obstructed pair of angles.
+fn reangle_pair(node1:&mut GallNode, node2:&mut GallNode, owners:&[Owner], obstacles:&Obstacles, margin:f64) {
+    let mut best_hits = obstacles.seg_hits(node_pos(node1), node_pos(node2), owners, margin);
+    let (Some(base1), Some(base2)) = (node1.ang(), node2.ang()) else {return};
+    let mut best = (base1, base2);
+    for offset1 in REANGLE_STEPS {
+        for offset2 in REANGLE_STEPS {
+            if best_hits == 0 {break;}
+            let (ang1, ang2) = (gall_ang::constrain(base1+offset1), gall_ang::constrain(base2+offset2));
+            node1.mut_ang(ang1);
+            node2.mut_ang(ang2);
+            let (start, end) = (node_pos(node1), node_pos(node2));
+            let direction = gall_ang::svg_ang2gall_ang((end.1-start.1).atan2(end.0-start.0));
+            if !node1.angle_test(direction) || !node2.angle_test(gall_ang::constrain(direction+PI)) {
+                continue;
+            }
+            let hits = obstacles.seg_hits(start, end, owners, margin);
+            if hits < best_hits {
+                best_hits = hits;
+                best = (ang1, ang2);
+            }
         }
     }
-    new_spare
+    node1.mut_ang(best.0);
+    node2.mut_ang(best.1);
 }
 
-pub fn generate_pairs(node_vec:Vec<&mut GallNode>) -> (Vec<GallLinePair>, Vec<&mut GallNode>) {
-    let mut rng = rand::thread_rng();
+//Aligns a pair's nodes, re-angles them if the line is obstructed and bends it in
+//curved mode. Gives the bend, if any, and how many obstacles the line still hits.
+fn route_pair(node1:&mut GallNode, node2:&mut GallNode, owners:&[Owner], obstacles:&Obstacles, config:&Config) -> (Option<(f64,f64)>, usize) {
+    let margin = f64::from(config.def_pair_thick);
+    align_nodes(node1, node2);
+    if obstacles.seg_hits(node_pos(node1), node_pos(node2), owners, margin) == 0 {
+        return (None, 0)
+    }
+    reangle_pair(node1, node2, owners, obstacles, margin);
+    let (start, end) = (node_pos(node1), node_pos(node2));
+    if config.connector_mode == ConnectorMode::Curved {
+        if let Some(control) = obstacles.bend(start, end, owners, margin) {
+            return (Some(control), obstacles.path_hits(&routing::quad_points(start, control, end), owners, margin))
+        }
+    }
+    (None, obstacles.seg_hits(start, end, owners, margin))
+}
+
+//Both nodes of a pair, mutably
+fn node_pair<'v>(node_vec:&'v mut [&mut GallNode], a:usize, b:usize) -> (&'v mut GallNode, &'v mut GallNode) {
+    let (low, high) = node_vec.split_at_mut(a.max(b));
+    let (first, second) = (&mut *low[a.min(b)], &mut *high[0]);
+    if a < b {(first, second)} else {(second, first)}
+}
+
+//Chooses the dash line pairs for the whole sentence at once: as many pairs as
+//the nodes allow, then the shortest total length with the fewest crossings and
+//obstructions. Obstructed pairs are then re-angled, and bent in curved mode.
+//Pairs neither clears are re-paired where the nodes allow it, so a line is only
+//drawn through what is in its way when no pairing avoids it. The score counts
+//the obstructions left once routed.
+pub fn generate_pairs<'a>(mut node_vec:Vec<&'a mut GallNode>, obstacles:&Obstacles, rng:&mut PairRng, config:&Config) -> (Vec<GallLinePair<'a>>, Vec<&'a mut GallNode>, PairScore) {
+    if node_vec.len() < 2 {
+        let score = PairScore { spares: node_vec.len(), ..PairScore::default() };
+        return (Vec::new(), node_vec, score)
+    }
+    let mut solver = PairSolver::new(&node_vec, obstacles, config);
+    let (mut matching, _) = solver.solve(rng);
+    let mut routes = Vec::new();
+    let mut given_up:Vec<(usize,usize)> = Vec::new();
+    for round in 0..=REPAIR_ROUNDS {
+        routes = matching.chosen.iter().map(|&(a,b)| {
+            let owners = [&solver.owners[a][..], &solver.owners[b]].concat();
+            let (node1, node2) = node_pair(&mut node_vec, a, b);
+            route_pair(node1, node2, &owners, obstacles, config)
+        }).collect();
+        let unrouted:Vec<(usize,usize)> = matching.chosen.iter().zip(&routes)
+            .filter(|&(&(a,b), &(_, hits))| hits > 0 && !given_up.contains(&(a.min(b),a.max(b))))
+            .map(|(&edge, _)| edge)
+            .collect();
+        if unrouted.is_empty() || round == REPAIR_ROUNDS {break;}
+        solver.repair(&mut matching, &unrouted);
+        given_up.extend(unrouted.iter().map(|&(a,b)| (a.min(b),a.max(b))));
+    }
+    let obstructions = routes.iter().map(|&(_, hits)| hits).sum();
+    let solved = solver.score(&matching);
+    let score = solver.scored(&matching, solved.crossings, obstructions, solved.length);
+    let mut slots:Vec<Option<&'a mut GallNode>> = node_vec.into_iter().map(Some).collect();
     let mut pair_list = Vec::new();
-    let length = node_vec.len();
-    let limit = match length {
-        0|1 => return (pair_list,node_vec),
-        len => len/2 + 1,
-    };
-    let mut retries:usize = 0;
-    let mut spare_list = base_loop(node_vec, &mut pair_list, &mut rng);
-    while spare_list.len() > 1 && retries < limit {
-        spare_list = base_loop(spare_list, &mut pair_list,&mut rng);
-        retries += 1;
-    }
-    (pair_list, spare_list)
-}
-pub fn extend_spares<'a>(spare_vec:Vec<&'a mut GallNode>, radius:Rc<Cell<f64>>, center:Rc<Cell<(f64,f64)>>) -> Vec<GallLine<'a>>{
-    let mut rng = rand::thread_rng();
+    for (&(a,b), (bend, _)) in matching.chosen.iter().zip(routes) {
+        let node1 = slots[a].take().expect("node paired twice");
+        let node2 = slots[b].take().expect("node paired twice");
+        let mut pair = GallLinePair::new(node1, node2, config);
+        pair.bend = bend;
+        pair_list.push(pair);
+    }
+    let spare_list = slots.into_iter().flatten().collect();
+    (pair_list, spare_list, score)
+}
+
+//Direction from a vowel dash's ring to its word centre
+fn inward_ang(node:&GallNode) -> Option<f64> {
+    let (x, y) = node.get_center().get();
+    let (w_x, w_y) = node.word_center()?.get();
+    if (w_x - x).hypot(w_y - y) < Config::COLLISION_DIST {
+        return node.vowel_dir()
+    }
+    Some(gall_ang::svg_ang2gall_ang((w_y - y).atan2(w_x - x)))
+}
+
+//Spare nodes run out to the sentence ring. Of the angles their `angle_test`
+//allows, the least obstructed one is used, starting the search at random.
+pub fn extend_spares<'a>(spare_vec:Vec<&'a mut GallNode>, radius:Rc<Cell<f64>>, center:Rc<Cell<(f64,f64)>>, obstacles:&Obstacles, rng:&mut PairRng, config:&Config) -> Vec<GallLine<'a>>{
     let mut lines = Vec::new();
+    let margin = f64::from(config.def_pair_thick);
     for node in spare_vec {
-        let mut ang = rng.gen_range(0.0..TAU);
-        while !node.angle_test(ang) {
-            ang = rng.gen_range(0.0..TAU);
-            println!("{}",ang)
+        let owners = ends(obstacles, &[&*node]);
+        if node.kind == NodeKind::VowelIn {
+            if let Some(ang) = inward_ang(node) {
+                node.mut_ang(ang);
+                lines.push(GallLine::new(node, radius.clone(), center.clone(), config));
+                continue;
+            }
+        }
+        let start = rng.gen_range(0.0..TAU);
+        let mut best:Option<(usize,f64)> = None;
+        for step in 0..SPARE_STEPS {
+            let ang = gall_ang::constrain(start + TAU*step as f64/SPARE_STEPS as f64);
+            if !node.angle_test(ang) {continue;}
+            let (x, y) = node.get_center().get();
+            let end = gall_pair::ring_point((x, y), ang, center.get(), radius.get());
+            let (sin, cos) = ang.sin_cos();
+            let trial = (x + node.dist()*sin, y + node.dist()*cos);
+            let hits = obstacles.seg_hits(trial, end, &owners, margin);
+            if best.is_none_or(|(best_hits, _)| hits < best_hits) {
+                best = Some((hits, ang));
+            }
         }
+        let ang = best.map_or_else(|| node.default_ang(), |(_, ang)| ang);
         node.mut_ang(ang);
-        lines.push(GallLine::new(node, radius.clone(), center.clone()));
+        let mut line = GallLine::new(node, radius.clone(), center.clone(), config);
+        if config.connector_mode == ConnectorMode::Curved {
+            line.bend = obstacles.bend(node_pos(node), line.get_endpoint(), &owners, margin);
+        }
+        lines.push(line);
     }
     lines
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Most pairs any matching of the solver's candidates can have, trying them all
+    fn max_pairs(solver:&PairSolver, matching:&mut Matching, from:usize) -> usize {
+        let size = solver.letters.len();
+        let Some(a) = (from..size).find(|&a| matching.mate[a].is_none()) else {
+            return 0
+        };
+        let mut best = max_pairs(solver, matching, a + 1);
+        for b in a+1..size {
+            if matching.mate[b].is_none() && solver.segs[a][b].is_some()
+            && unique_pair_test(solver.letters[a], solver.letters[b], &matching.chosen, &solver.letters) {
+                matching.mate[a] = Some(b);
+                matching.mate[b] = Some(a);
+                matching.chosen.push((a,b));
+                best = best.max(1 + max_pairs(solver, matching, a + 1));
+                matching.chosen.pop();
+                matching.mate[a] = None;
+                matching.mate[b] = None;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn solver_finds_most_pairs() {
+        let config = Config::default();
+        //small enough to try every matching
+        for phrase in ["vim", "snow queen", "gun fun sun", "swim wing"] {
+            let mut sent = crate::layout(phrase, &config).unwrap();
+            let obstacles = Obstacles::from_sentence(&sent);
+            let node_vec = sent.collect_nodes();
+            let solver = PairSolver::new(&node_vec, &obstacles, &config);
+            let mut empty = Matching { mate: vec![None; node_vec.len()], chosen: Vec::new() };
+            let most = max_pairs(&solver, &mut empty, 0);
+            assert_eq!(solver.solve(&mut seeded_rng(1)).1.pairs, most, "{}", phrase);
+        }
+    }
+
+    #[test]
+    fn routing_clears_obstructions() {
+        let phrase = "the quick brown fox jumps over the lazy dog";
+        for connector_mode in [ConnectorMode::Straight, ConnectorMode::Curved] {
+            let config = Config {
+                connector_mode,
+                ..Config::default()
+            };
+            let mut sent = crate::layout(phrase, &config).unwrap();
+            let obstacles = Obstacles::from_sentence(&sent);
+            let node_vec = sent.collect_nodes();
+            let unrouted = PairSolver::new(&node_vec, &obstacles, &config).solve(&mut seeded_rng(3)).1;
+            let (_, _, routed) = generate_pairs(node_vec, &obstacles, &mut seeded_rng(3), &config);
+            assert!(unrouted.obstructions > 0);
+            assert_eq!(routed.pairs, unrouted.pairs);
+            assert!(routed.obstructions < unrouted.obstructions);
+            if connector_mode == ConnectorMode::Curved {
+                assert_eq!(routed.obstructions, 0);
+            }
+        }
+    }
+
+    //96 words, rescoring the whole matching on every move took minutes here
+    #[test]
+    fn long_phrase_pairs_quickly() {
+        let words = "the quick brown fox jumps over the lazy dog while five wizards box with jacks";
+        let phrase = [words; 6].join(" ");
+        let config = Config::default();
+        let mut sent = crate::layout(&phrase, &config).unwrap();
+        let obstacles = Obstacles::from_sentence(&sent);
+        let start = std::time::Instant::now();
+        let (pairs, _, score) = generate_pairs(sent.collect_nodes(), &obstacles, &mut seeded_rng(1), &config);
+        assert!(start.elapsed() < std::time::Duration::from_secs(20), "took {:?}", start.elapsed());
+        assert_eq!(pairs.len(), score.pairs);
+    }
+
+    #[test]
+    fn same_seed_same_svg() {
+        for connector_mode in [ConnectorMode::Straight, ConnectorMode::Curved] {
+            let config = Config {
+                connector_mode,
+                ..Config::default()
+            };
+            let render = |seed| crate::render_svg_string("the quick brown fox jumps over the wing", &config, seed).unwrap();
+            assert_eq!(render(7), render(7));
+            assert_eq!(render(u64::MAX), render(u64::MAX));
+        }
+    }
+}
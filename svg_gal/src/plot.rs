@@ -0,0 +1,545 @@
+use std::f64::consts::{PI, TAU};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gall_circle::{ChildCircle, Circle, HollowCircle};
+use crate::gall_config::Config;
+use crate::gall_loc::Location;
+use crate::gall_mark::MarkPart;
+use crate::gall_pair::{GallLine, GallLinePair};
+use crate::gall_sentence::GallSentence;
+use crate::gall_stem::StemType;
+use crate::gall_word::GallWord;
+use crate::render::ViewBox;
+
+//Single stroke geometry for pens and cutters, in canvas units with y pointing
+//down as in the SVG. Strokes are centre lines, outlines are the filled shapes' edges.
+
+pub type Point = (f64,f64);
+
+//Curves are drawn as lines about this long (in mm)
+const QUAD_STEP: f64 = 1.0;
+const MAX_QUAD_STEPS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DotFill {
+    //an outline with a zigzag inside, one spacing per pen width
+    #[default]
+    Hatch,
+    Outline,
+}
+
+//Where the machine's 0,0 sits on the drawing. Bottom left and centre have y
+//pointing up, top left keeps the SVG's y pointing down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlotOrigin {
+    #[default]
+    BottomLeft,
+    TopLeft,
+    Centre,
+}
+
+//Which part of the drawing a stroke belongs to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Sentence,
+    Words,
+    Letters,
+    Vowels,
+    Decorations,
+    Connectors,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Line(Point),
+    //`clockwise` as seen on screen, matching the SVG sweep flag
+    Arc{centre:Point, end:Point, clockwise:bool},
+    //quadratic Bezier control point and end, for curved connectors
+    Quad(Point, Point),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle(Point, f64),
+    Path(Point, Vec<Segment>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub layer: Layer,
+    pub shape: Shape,
+}
+
+//A run drawn without lifting the pen
+#[derive(Clone, Debug, PartialEq)]
+pub struct PenPath {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+}
+
+//Maps canvas units to millimetres on the machine, `plot_width_mm` across the view
+pub struct Sheet {
+    pub scale: f64,
+    pub flip: bool,
+    shift: Point,
+    home: Point,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 6] = [
+        Layer::Sentence,
+        Layer::Words,
+        Layer::Letters,
+        Layer::Vowels,
+        Layer::Decorations,
+        Layer::Connectors,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Sentence => "sentence",
+            Layer::Words => "words",
+            Layer::Letters => "letters",
+            Layer::Vowels => "vowels",
+            Layer::Decorations => "decorations",
+            Layer::Connectors => "connectors",
+        }
+    }
+}
+
+impl Segment {
+    pub fn end(&self) -> Point {
+        match self {
+            Segment::Line(end) => *end,
+            Segment::Arc{end, ..} => *end,
+            Segment::Quad(_, end) => *end,
+        }
+    }
+    //The same segment drawn back to `start`
+    fn reversed(&self, start:Point) -> Segment {
+        match *self {
+            Segment::Line(_) => Segment::Line(start),
+            Segment::Arc{centre, clockwise, ..} => Segment::Arc{centre, end:start, clockwise:!clockwise},
+            Segment::Quad(control, _) => Segment::Quad(control, start),
+        }
+    }
+}
+
+//Radius and whether an arc from `start` turns more than half way round
+pub fn arc_size(start:Point, centre:Point, end:Point, clockwise:bool) -> (f64,bool) {
+    let (start_ang, end_ang) = (angle(centre, start), angle(centre, end));
+    let turn = if clockwise {end_ang - start_ang} else {start_ang - end_ang};
+    (dist(centre, start), turn.rem_euclid(TAU) > PI)
+}
+
+impl Sheet {
+    pub fn new(view:ViewBox, config:&Config) -> Sheet {
+        let scale = config.plot_width_mm/view.2;
+        let margin = config.plot_margin_mm;
+        let (x, y, width, height) = view;
+        let (shift, home, flip) = match config.plot_origin {
+            PlotOrigin::BottomLeft => ((margin - x*scale, margin + (y + height)*scale), (x, y + height), true),
+            PlotOrigin::TopLeft => ((margin - x*scale, margin - y*scale), (x, y), false),
+            PlotOrigin::Centre => ((-(x + width/2.0)*scale, (y + height/2.0)*scale), (x + width/2.0, y + height/2.0), true),
+        };
+        Sheet {
+            scale,
+            flip,
+            shift,
+            home,
+        }
+    }
+    pub fn place(&self, point:Point) -> Point {
+        let y = if self.flip {-point.1} else {point.1};
+        (self.shift.0 + point.0*self.scale, self.shift.1 + y*self.scale)
+    }
+    //CAD always has y up, so a top left origin puts the drawing below the x axis
+    pub fn upright(view:ViewBox, config:&Config) -> Sheet {
+        let mut sheet = Sheet::new(view, config);
+        if !sheet.flip {
+            sheet.flip = true;
+            sheet.shift.1 = -sheet.shift.1;
+        }
+        sheet
+    }
+    //The canvas point the machine starts from
+    pub fn home(&self) -> Point {
+        self.home
+    }
+    //G-code and DXF turn anticlockwise with y up, so the SVG's clockwise
+    //arcs stay clockwise only when y is flipped
+    pub fn clockwise(&self, screen_clockwise:bool) -> bool {
+        screen_clockwise == self.flip
+    }
+}
+
+impl Stroke {
+    fn new(layer:Layer, shape:Shape) -> Stroke {
+        Stroke {
+            layer,
+            shape,
+        }
+    }
+    //How far the pen has to travel from `pen` to start drawing this
+    fn gap(&self, pen:Point) -> f64 {
+        match &self.shape {
+            Shape::Circle(centre, radius) => (dist(pen, *centre) - radius).abs(),
+            Shape::Path(start, segments) => {
+                let end = segments.last().map_or(*start, Segment::end);
+                dist(pen, *start).min(dist(pen, end))
+            },
+        }
+    }
+    //Circles start at the point nearest `pen`, paths at whichever end is nearer
+    fn enter(self, pen:Point) -> PenPath {
+        match self.shape {
+            Shape::Circle(centre, radius) => {
+                let ang = if pen == centre {0.0} else {angle(centre, pen)};
+                let start = polar(centre, radius, ang);
+                let half = polar(centre, radius, ang + PI);
+                PenPath {
+                    start,
+                    segments: vec![
+                        Segment::Arc{centre, end:half, clockwise:true},
+                        Segment::Arc{centre, end:start, clockwise:true},
+                    ],
+                }
+            },
+            Shape::Path(start, segments) => {
+                let end = segments.last().map_or(start, Segment::end);
+                if dist(pen, end) < dist(pen, start) {
+                    let mut points = vec![start];
+                    points.extend(segments.iter().map(Segment::end));
+                    let segments = segments.iter().enumerate().rev()
+                        .map(|(n, segment)| segment.reversed(points[n]))
+                        .collect();
+                    PenPath {
+                        start: end,
+                        segments,
+                    }
+                } else {
+                    PenPath {
+                        start,
+                        segments,
+                    }
+                }
+            },
+        }
+    }
+}
+
+//Orders the strokes nearest first from `pen`, chaining any that start within
+//`join` of where the last one ended so the pen stays down
+pub fn pen_paths(mut strokes:Vec<Stroke>, mut pen:Point, join:f64) -> Vec<PenPath> {
+    let mut paths:Vec<PenPath> = Vec::new();
+    while !strokes.is_empty() {
+        let (index, gap) = strokes.iter()
+            .map(|stroke| stroke.gap(pen))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        let next = strokes.remove(index).enter(pen);
+        pen = next.segments.last().map_or(next.start, Segment::end);
+        match paths.last_mut() {
+            Some(last) if gap <= join => {
+                if gap > 0.0 {
+                    last.segments.push(Segment::Line(next.start));
+                }
+                last.segments.extend(next.segments);
+            },
+            _ => paths.push(next),
+        }
+    }
+    paths
+}
+
+//Dash lines, taken before the sentence as they borrow its nodes
+pub fn connector_strokes(pairs:&[GallLinePair], lines:&[GallLine]) -> Vec<Stroke> {
+    let ends = pairs.iter()
+        .map(|pair| ((pair.node1.x(), pair.node1.y()), (pair.node2.x(), pair.node2.y()), pair.bend))
+        .chain(lines.iter().map(|line| ((line.node.x(), line.node.y()), line.get_endpoint(), line.bend)));
+    ends.map(|(start, end, bend)| {
+        let segment = match bend {
+            Some(control) => Segment::Quad(control, end),
+            None => Segment::Line(end),
+        };
+        Stroke::new(Layer::Connectors, Shape::Path(start, vec![segment]))
+    }).collect()
+}
+
+pub fn sentence_strokes(sent:&GallSentence, config:&Config) -> Vec<Stroke> {
+    let mut strokes = vec![Stroke::new(Layer::Sentence, Shape::Circle((sent.x(), sent.y()), sent.radius()))];
+    for word in &sent.words {
+        word_strokes(word, &mut strokes, config);
+    }
+    for mark in &sent.marks {
+        for part in mark.parts() {
+            match part {
+                MarkPart::Ring(centre, radius) => strokes.push(Stroke::new(Layer::Decorations, Shape::Circle(centre, radius))),
+                MarkPart::Dot(centre, radius) => strokes.push(dot_stroke(centre, radius, config)),
+                MarkPart::Tick(start, end) => strokes.push(line_stroke(Layer::Decorations, (start, end))),
+            }
+        }
+    }
+    strokes
+}
+
+//The word line runs round the word and dips along the inside of each B and
+//S letter it meets. Stacked letters behind the first keep just their inside arc.
+fn word_strokes(word:&GallWord, strokes:&mut Vec<Stroke>, config:&Config) {
+    let centre = (word.x(), word.y());
+    let radius = word.radius();
+    let mut cuts = Vec::new();
+    for tainer in &word.tainer_vec {
+        let on_line = matches!(tainer.stem_type(), Some(StemType::B|StemType::S));
+        for (n, stem) in tainer.stem.iter().enumerate() {
+            let stem_cent = (stem.x(), stem.y());
+            match (on_line, crossing(centre, radius, stem_cent, stem.radius())) {
+                (true, Some(cut)) if n == 0 => cuts.push(cut),
+                (true, Some(cut)) => strokes.push(cut_stroke(centre, radius, cut)),
+                _ => strokes.push(Stroke::new(Layer::Letters, Shape::Circle(stem_cent, stem.radius()))),
+            }
+        }
+        for vowel in &tainer.vowel {
+            strokes.push(Stroke::new(Layer::Vowels, Shape::Circle((vowel.x(), vowel.y()), vowel.radius())));
+        }
+        for dot in &tainer.dot {
+            strokes.push(dot_stroke((dot.x(), dot.y()), dot.radius(), config));
+        }
+        for line in &tainer.line {
+            strokes.push(line_stroke(Layer::Letters, line.ends()));
+        }
+    }
+    strokes.push(word_loop(centre, radius, cuts));
+}
+
+//The edges of what the filled render draws: both sides of every ring, the
+//word skeleton outlines `part_render` traces, and the edges of stacked letters.
+//Dots are their edge and the thin lines (digits, ticks, dash lines) stay centre lines.
+pub fn sentence_outlines(sent:&GallSentence) -> Vec<Stroke> {
+    let mut strokes = Vec::new();
+    ring_strokes(Layer::Sentence, (sent.x(), sent.y()), sent.radius(), sent.thick(), &mut strokes);
+    for word in &sent.words {
+        word_outlines(word, &mut strokes);
+    }
+    for mark in &sent.marks {
+        for part in mark.parts() {
+            match part {
+                MarkPart::Ring(centre, radius) => ring_strokes(Layer::Decorations, centre, radius, mark.parent_thick()/2.0, &mut strokes),
+                MarkPart::Dot(centre, radius) => strokes.push(Stroke::new(Layer::Decorations, Shape::Circle(centre, radius))),
+                MarkPart::Tick(start, end) => strokes.push(line_stroke(Layer::Decorations, (start, end))),
+            }
+        }
+    }
+    strokes
+}
+
+//As `part_render`, the inner outline dips along the outside of the first stem
+//and the outer outline along the inside of the last (the first for B). The
+//other stem edges are cut off at the word's inner edge for S and outer for B.
+fn word_outlines(word:&GallWord, strokes:&mut Vec<Stroke>) {
+    let centre = (word.x(), word.y());
+    let (inner, outer) = (word.inner_radius(), word.outer_radius());
+    let (mut inner_cuts, mut outer_cuts) = (Vec::new(), Vec::new());
+    for tainer in &word.tainer_vec {
+        let stem_type = tainer.stem_type().copied();
+        let edge = if stem_type == Some(StemType::B) {outer} else {inner};
+        let last = if stem_type == Some(StemType::B) {0} else {tainer.stem.len().saturating_sub(1)};
+        for (n, stem) in tainer.stem.iter().enumerate() {
+            let stem_cent = (stem.x(), stem.y());
+            if !matches!(stem_type, Some(StemType::B|StemType::S)) {
+                ring_strokes(Layer::Letters, stem_cent, stem.radius(), stem.thick(), strokes);
+                continue
+            }
+            for (stem_radius, on_loop, word_radius, cuts) in [
+                (stem.outer_radius(), n == 0, inner, &mut inner_cuts),
+                (stem.inner_radius(), n == last, outer, &mut outer_cuts),
+            ] {
+                let word_radius = if on_loop {word_radius} else {edge};
+                match crossing(centre, word_radius, stem_cent, stem_radius) {
+                    Some(cut) if on_loop => cuts.push(cut),
+                    Some(cut) => strokes.push(cut_stroke(centre, word_radius, cut)),
+                    None if stem_radius > 0.0 => strokes.push(Stroke::new(Layer::Letters, Shape::Circle(stem_cent, stem_radius))),
+                    None => (),
+                }
+            }
+        }
+        for vowel in &tainer.vowel {
+            ring_strokes(Layer::Vowels, (vowel.x(), vowel.y()), vowel.radius(), vowel.thick(), strokes);
+        }
+        for dot in &tainer.dot {
+            strokes.push(Stroke::new(Layer::Decorations, Shape::Circle((dot.x(), dot.y()), dot.radius())));
+        }
+        for line in &tainer.line {
+            strokes.push(line_stroke(Layer::Letters, line.ends()));
+        }
+    }
+    strokes.push(word_loop(centre, inner, inner_cuts));
+    strokes.push(word_loop(centre, outer, outer_cuts));
+}
+
+//The circle round `centre` clockwise, dipping along the inside arc of each cut.
+//With no cuts it is a plain circle.
+fn word_loop(centre:Point, radius:f64, mut cuts:Vec<(f64,f64,Point)>) -> Stroke {
+    if cuts.is_empty() {
+        return Stroke::new(Layer::Words, Shape::Circle(centre, radius))
+    }
+    cuts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let start = polar(centre, radius, cuts[cuts.len() - 1].1);
+    let mut segments = Vec::with_capacity(2*cuts.len());
+    for (start_ang, end_ang, stem_cent) in cuts {
+        let cut_start = polar(centre, radius, start_ang);
+        segments.push(Segment::Arc{centre, end:cut_start, clockwise:true});
+        segments.push(inside_arc(stem_cent, cut_start, polar(centre, radius, end_ang), centre));
+    }
+    Stroke::new(Layer::Words, Shape::Path(start, segments))
+}
+
+//Just the inside arc of a cut, for stacked letters off the word line
+fn cut_stroke(centre:Point, radius:f64, (start_ang, end_ang, stem_cent):(f64,f64,Point)) -> Stroke {
+    let start = polar(centre, radius, start_ang);
+    let arc = inside_arc(stem_cent, start, polar(centre, radius, end_ang), centre);
+    Stroke::new(Layer::Letters, Shape::Path(start, vec![arc]))
+}
+
+//Both edges of a ring `thick` either side of `radius`
+fn ring_strokes(layer:Layer, centre:Point, radius:f64, thick:f64, strokes:&mut Vec<Stroke>) {
+    strokes.push(Stroke::new(layer, Shape::Circle(centre, radius + thick)));
+    if radius > thick {
+        strokes.push(Stroke::new(layer, Shape::Circle(centre, radius - thick)));
+    }
+}
+
+fn line_stroke(layer:Layer, (start, end):(Point,Point)) -> Stroke {
+    Stroke::new(layer, Shape::Path(start, vec![Segment::Line(end)]))
+}
+
+//Where the circle of `stem_radius` round `stem_cent` crosses the circle round
+//`centre`, as angles about `centre` in clockwise order, along with `stem_cent`
+fn crossing(centre:Point, radius:f64, stem_cent:Point, stem_radius:f64) -> Option<(f64,f64,Point)> {
+    let apart = dist(centre, stem_cent);
+    if apart < Config::COLLISION_DIST {
+        return None
+    }
+    let cos = (radius*radius + apart*apart - stem_radius*stem_radius)/(2.0*radius*apart);
+    if cos.abs() >= 1.0 {
+        return None
+    }
+    let ang = angle(centre, stem_cent);
+    let spread = cos.acos();
+    Some((ang - spread, ang + spread, stem_cent))
+}
+
+//The arc round `centre` from `start` to `end` on the side facing `inward`
+fn inside_arc(centre:Point, start:Point, end:Point, inward:Point) -> Segment {
+    let start_ang = angle(centre, start);
+    let turn = |ang:f64| (ang - start_ang).rem_euclid(TAU);
+    let clockwise = turn(angle(centre, inward)) < turn(angle(centre, end));
+    Segment::Arc{centre, end, clockwise}
+}
+
+//Dots too small for the pen are a touch of the pen on their centre
+fn dot_stroke(centre:Point, radius:f64, config:&Config) -> Stroke {
+    let pen = config.pen_width;
+    let outline = radius - pen/2.0;
+    if outline <= 0.0 {
+        return Stroke::new(Layer::Decorations, Shape::Path(centre, vec![Segment::Line(centre)]))
+    }
+    if config.dot_fill == DotFill::Outline || outline <= pen {
+        return Stroke::new(Layer::Decorations, Shape::Circle(centre, outline))
+    }
+    //the outline from the first row's left end, then back and forth across
+    //in evenly spaced rows no further apart than the pen is wide
+    let rows = (2.0*outline/pen).ceil() as usize - 1;
+    let row_end = |row:usize, left:bool| {
+        let y = outline*(2.0*(row + 1) as f64/(rows + 1) as f64 - 1.0);
+        let x = (outline*outline - y*y).max(0.0).sqrt();
+        (centre.0 + if left {-x} else {x}, centre.1 + y)
+    };
+    let start = row_end(0, true);
+    let ang = angle(centre, start);
+    let mut segments = vec![
+        Segment::Arc{centre, end:polar(centre, outline, ang + PI), clockwise:true},
+        Segment::Arc{centre, end:start, clockwise:true},
+    ];
+    for row in 0..rows {
+        let from_left = row % 2 == 0;
+        if row > 0 {
+            segments.push(Segment::Line(row_end(row, from_left)));
+        }
+        segments.push(Segment::Line(row_end(row, !from_left)));
+    }
+    Stroke::new(Layer::Decorations, Shape::Path(start, segments))
+}
+
+//The ends of lines about `QUAD_STEP` mm long along a quadratic Bezier, for
+//outputs without curves, `scale` being mm per canvas unit
+pub fn quad_lines(start:Point, control:Point, end:Point, scale:f64) -> Vec<Point> {
+    let length = (dist(start, control) + dist(control, end))*scale;
+    let steps = ((length/QUAD_STEP).ceil() as usize).clamp(1, MAX_QUAD_STEPS);
+    quad_points(start, control, end, steps)
+}
+
+//Points along a quadratic Bezier
+pub fn quad_points(start:Point, control:Point, end:Point, steps:usize) -> Vec<Point> {
+    (1..=steps).map(|step| {
+        let t = step as f64/steps as f64;
+        let (a, b, c) = ((1.0 - t)*(1.0 - t), 2.0*(1.0 - t)*t, t*t);
+        (a*start.0 + b*control.0 + c*end.0, a*start.1 + b*control.1 + c*end.1)
+    }).collect()
+}
+
+fn polar(centre:Point, radius:f64, ang:f64) -> Point {
+    (centre.0 + radius*ang.cos(), centre.1 + radius*ang.sin())
+}
+fn angle(from:Point, to:Point) -> f64 {
+    (to.1 - from.1).atan2(to.0 - from.0)
+}
+pub fn dist(a:Point, b:Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(layer:Layer, start:Point, end:Point) -> Stroke {
+        Stroke::new(layer, Shape::Path(start, vec![Segment::Line(end)]))
+    }
+
+    #[test]
+    fn pen_paths_chain_nearby_strokes() {
+        let strokes = vec![
+            line(Layer::Letters, (50.0, 0.0), (10.0, 0.0)),
+            line(Layer::Letters, (0.0, 0.0), (9.5, 0.0)),
+            Stroke::new(Layer::Words, Shape::Circle((100.0, 0.0), 10.0)),
+        ];
+        let paths = pen_paths(strokes, (0.0, 0.0), 1.0);
+        //the gap of 0.5 is bridged, the reversed line is entered from its near end
+        assert_eq!(paths[0], PenPath {
+            start: (0.0, 0.0),
+            segments: vec![Segment::Line((9.5, 0.0)), Segment::Line((10.0, 0.0)), Segment::Line((50.0, 0.0))],
+        });
+        //the circle starts where the pen is nearest and closes on itself
+        assert_eq!(paths.len(), 2);
+        let end = paths[1].segments.last().map(Segment::end).unwrap();
+        assert!(dist(paths[1].start, (90.0, 0.0)) < 1e-9);
+        assert_eq!(end, paths[1].start);
+    }
+
+    #[test]
+    fn sheet_flips_bottom_left() {
+        let config = Config {
+            plot_width_mm: 100.0,
+            plot_margin_mm: 5.0,
+            plot_origin: PlotOrigin::BottomLeft,
+            ..Config::default()
+        };
+        let sheet = Sheet::new((0.0, 0.0, 200.0, 100.0), &config);
+        assert_eq!(sheet.place(sheet.home()), (5.0, 5.0));
+        assert_eq!(sheet.place((200.0, 0.0)), (105.0, 55.0));
+        assert!(sheet.clockwise(true));
+    }
+}
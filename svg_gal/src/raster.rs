@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::Path;
+
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, Options, Tree};
+use svg::Document;
+
+use crate::gall_errors::{Error, GallError};
+use crate::render::ViewBox;
+
+//SVG user units are treated as CSS pixels, which are defined at 96 DPI
+pub const BASE_DPI: f32 = 96.0;
+//Largest image drawn, 8192 x 8192. The pixmap holds 4 bytes a pixel, so this
+//keeps a PNG export to 256MB of memory.
+pub const MAX_PIXELS: u64 = 1 << 26;
+//tEXt keyword the dash line seed is stored under, as SVGs keep it in data-seed
+pub const SEED_KEY: &str = "svg_gal:seed";
+//PNG signature plus the IHDR chunk, which has to come first
+const HEADER_LEN: usize = 8 + 25;
+
+//Picks the output pixel size, keeping the aspect ratio of the drawn view
+//(the canvas, or the area round the sentence with `fit_canvas`).
+//An explicit width wins over dpi, which scales the view size.
+pub fn pixel_size(view:ViewBox, width:Option<u32>, dpi:Option<f32>) -> Result<(u32,u32), Error> {
+    let aspect = view.3/view.2;
+    let width = match (width, dpi) {
+        (Some(0), _) => return Err(export_err("image width must be at least 1 pixel".to_string())),
+        (Some(width), _) => f64::from(width),
+        (None, Some(dpi)) if !(dpi > 0.0 && dpi.is_finite()) => {
+            return Err(export_err(format!("DPI must be above 0, not {}", dpi)))
+        },
+        (None, Some(dpi)) => view.2 * f64::from(dpi/BASE_DPI),
+        (None, None) => view.2,
+    };
+    let (width, height) = (width.round().max(1.0), (width*aspect).round().max(1.0));
+    if width*height > MAX_PIXELS as f64 {
+        return Err(export_err(format!("{}x{} is over the {} pixel limit", width, height, MAX_PIXELS)))
+    }
+    Ok((width as u32, height as u32))
+}
+
+pub fn render_png(drawn:&Document, size:(u32,u32), seed:u64) -> Result<Vec<u8>, Error> {
+    if u64::from(size.0)*u64::from(size.1) > MAX_PIXELS {
+        return Err(export_err(format!("{}x{} is over the {} pixel limit", size.0, size.1, MAX_PIXELS)))
+    }
+    //Gallifreyan has no text elements, so an empty font database is enough
+    let tree = Tree::from_str(&drawn.to_string(), &Options::default(), &fontdb::Database::new())
+        .map_err(|e| export_err(e.to_string()))?;
+    let mut pixmap = Pixmap::new(size.0, size.1)
+        .ok_or_else(|| export_err(format!("invalid image size {}x{}", size.0, size.1)))?;
+    let tree_size = tree.size();
+    let transform = Transform::from_scale(
+        size.0 as f32 / tree_size.width(),
+        size.1 as f32 / tree_size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    let mut data = pixmap.encode_png().map_err(|e| export_err(e.to_string()))?;
+    let text = text_chunk(SEED_KEY, &seed.to_string());
+    data.splice(HEADER_LEN..HEADER_LEN, text);
+    Ok(data)
+}
+
+pub fn save_png<P:AsRef<Path>>(path:P, drawn:&Document, size:(u32,u32), seed:u64) -> Result<(), Error> {
+    let data = render_png(drawn, size, seed)?;
+    fs::write(path, data).map_err(|e| export_err(e.to_string()))
+}
+
+//A tEXt chunk: length, type, keyword and text split by a nul, then the CRC of type and data
+fn text_chunk(key:&str, text:&str) -> Vec<u8> {
+    let mut body = b"tEXt".to_vec();
+    body.extend(key.bytes());
+    body.push(0);
+    body.extend(text.bytes());
+    let mut chunk = ((body.len() - 4) as u32).to_be_bytes().to_vec();
+    chunk.extend(&body);
+    chunk.extend(crc32(&body).to_be_bytes());
+    chunk
+}
+
+fn crc32(bytes:&[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {(crc >> 1) ^ 0xEDB8_8320} else {crc >> 1};
+        }
+    }
+    !crc
+}
+
+fn export_err(message:String) -> Error {
+    Error::new(GallError::ExportFailed(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_size_bounds() {
+        let view = (0.0, 0.0, 1000.0, 500.0);
+        assert_eq!(pixel_size(view, None, None).unwrap(), (1000, 500));
+        assert_eq!(pixel_size(view, Some(300), None).unwrap(), (300, 150));
+        assert_eq!(pixel_size(view, None, Some(192.0)).unwrap(), (2000, 1000));
+        assert_eq!(pixel_size(view, Some(1), None).unwrap(), (1, 1));
+        assert!(pixel_size(view, Some(0), None).is_err());
+        assert!(pixel_size(view, None, Some(0.0)).is_err());
+        assert!(pixel_size(view, None, Some(f32::NAN)).is_err());
+        assert!(pixel_size(view, Some(100_000), None).is_err());
+        assert!(pixel_size(view, Some(u32::MAX), None).is_err());
+        assert!(render_png(&Document::new(), (100_000, 100_000), 1).is_err());
+    }
+
+    #[test]
+    fn png_keeps_seed() {
+        let config = crate::Config::default();
+        let drawn = crate::render_phrase("hello", &config, 42).unwrap();
+        let data = render_png(&drawn, (64, 64), 42).unwrap();
+        //walk the chunks as a reader would, checking each CRC
+        let mut found = None;
+        let mut at = 8;
+        while at < data.len() {
+            let len = u32::from_be_bytes(data[at..at+4].try_into().unwrap()) as usize;
+            let body = &data[at+4..at+8+len];
+            assert_eq!(&data[at+8+len..at+12+len], crc32(body).to_be_bytes());
+            if let Some(text) = body.strip_prefix(b"tEXt") {
+                found = Some(String::from_utf8(text.to_vec()).unwrap());
+            }
+            at += 12 + len;
+        }
+        assert_eq!(found.as_deref(), Some("svg_gal:seed\u{0}42"));
+    }
+}
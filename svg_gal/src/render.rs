@@ -1,14 +1,18 @@
 use std::f64::consts::{PI, TAU};
 
 use svg::Document;
-use svg::node::element::{Circle, Element, Line, Path, Rectangle};
+use svg::node::Node;
+use svg::node::Text;
+use svg::node::element::{Circle, Description, Element, Group, Line, Path, Rectangle, Title};
 use svg::node::element::path::Data;
 
 use crate::gall_circle::{ChildCircle, Circle as Cir, Dot, HollowCircle};
 use crate::gall_config::Config;
-use crate::gall_errors::Error;
+use crate::gall_errors::{Error, GallError};
 use crate::gall_loc::{GallLoc, Location};
+use crate::gall_mark::{GallMark, MarkPart};
 use crate::gall_node::GallNode;
+use crate::gall_num::DigitLine;
 use crate::gall_ord::PolarOrdinate;
 use crate::gall_pair::{GallLine, GallLinePair};
 use crate::gall_sentence::GallSentence;
@@ -16,43 +20,70 @@ use crate::gall_stem::{Stem, StemType};
 use crate::gall_tainer::GallTainer;
 use crate::gall_vowel::GallVowel;
 use crate::gall_word::GallWord;
+use crate::plot::{self, PenPath, Segment, Stroke};
 
 pub trait Renderable {
-    fn render(self, drawn:Document) -> Document; 
+    fn render(self, drawn:Document, config:&Config) -> Result<Document, Error>;
 }
 
-trait SkelPart {
-    fn part_init(&self) -> ((Data, Data),(f64,f64),(f64,f64),(f64,f64));
-    fn part_render(&self, inner_outer:(Data,Data), start_ang:(f64,f64)) -> Result<((Data,Data),(f64,f64)), Error>;
-}
+//inner & outer skeleton path data
+type SkelData = (Data, Data);
+//path data, inner & outer join points, inner & outer start angles
+type SkelInit = (SkelData, (f64,f64), (f64,f64), (f64,f64));
 
-trait FreeRender {
-    fn post_render(&self, vec:&mut Vec<Element>);
+trait SkelPart {
+    fn part_init(&self) -> Result<SkelInit, Error>;
+    fn part_render(&self, inner_outer:SkelData, start_ang:(f64,f64)) -> Result<(SkelData,(f64,f64)), Error>;
 }
 
 trait Basic {
-    fn get_shape(&self) -> Element;
+    fn get_shape(&self, config:&Config) -> Element;
 }
 impl<T:Basic> Renderable for T {
-    fn render(self, drawn:Document) -> Document {
-        drawn.add(self.get_shape())
+    fn render(self, drawn:Document, config:&Config) -> Result<Document, Error> {
+        Ok(drawn.add(self.get_shape(config)))
     }
 }
-impl<T:Basic> FreeRender for T {
-    fn post_render(&self, vec:&mut Vec<Element>) {
-        vec.push(self.get_shape().into())
+
+//x, y, width, height of the visible area
+pub type ViewBox = (f64,f64,f64,f64);
+
+//The whole canvas, or with `fit_canvas` just the sentence plus `canvas_margin`,
+//widened to keep the canvas aspect ratio
+pub fn view_box(sent:&GallSentence, config:&Config) -> ViewBox {
+    if !config.fit_canvas {
+        return (0.0, 0.0, config.width, config.height)
     }
+    let (x, y) = (sent.x(), sent.y());
+    let reach = sent.reach(config) + config.canvas_margin;
+    let aspect = config.height/config.width;
+    let (half_w, half_h) = if aspect > 1.0 {(reach, reach*aspect)} else {(reach/aspect, reach)};
+    (x - half_w, y - half_h, 2.0*half_w, 2.0*half_h)
 }
 
-pub fn create_svg() -> Document {
-    let drawn = Document::new().set("viewBox", (0, 0, Config::WIDTH, Config::HEIGHT));   
-    if Config::ENABLE_CANVAS {
+//With `accessible` the phrase goes in a <title>, <desc> and aria-label,
+//so the picture isn't blank to screen readers and search engines
+pub fn create_svg(view:ViewBox, phrase:&str, config:&Config) -> Document {
+    let mut drawn = Document::new().set("viewBox", view);   
+    if config.accessible {
+        let title = fill_phrase(&config.svg_title, phrase, phrase);
+        let desc = fill_phrase(&config.svg_description, phrase, "Sherman's Circular Gallifreyan writing of: {phrase}");
+        drawn = drawn
+            .set("role", "img")
+            .set("aria-label", title.as_str())
+            .set("aria-labelledby", "svg-title")
+            .set("aria-describedby", "svg-desc")
+            .add(Title::new(title).set("id", "svg-title"))
+            .add(Description::new().set("id", "svg-desc").add(Text::new(desc)));
+    }
+    if config.enable_canvas && !config.line_art {
         let background = Rectangle::new()
-        .set("x", 0)
-        .set("y", 0)
-        .set("width", Config::WIDTH)
-        .set("height", Config::HEIGHT)
-        .set("fill", Config::CANVAS_COLOUR())
+        .set("x", view.0)
+        .set("y", view.1)
+        .set("width", view.2)
+        .set("height", view.3)
+        .set("class", "canvas")
+        .set("fill", config.canvas_colour.as_str())
         .set("stroke", "none");
         drawn.add(background)
     } else {
@@ -61,85 +92,203 @@ pub fn create_svg() -> Document {
     
 }
 
-pub fn render_init(pairs:Vec<GallLinePair>, lines: Vec<GallLine>) -> (Document,Vec<Element>) {
-    let mut post_render = Vec::new();
-    for pair in pairs {
-        pair.post_render(&mut post_render)
+//Dash lines are drawn last, on top of everything, in a group of their own
+pub fn render_init(pairs:Vec<GallLinePair>, lines: Vec<GallLine>, view:ViewBox, phrase:&str, config:&Config) -> (Document,Group) {
+    let mut dash_lines = Group::new()
+        .set("id", "dash-lines")
+        .set("class", "dash-lines");
+    for (n, pair) in pairs.iter().enumerate() {
+        dash_lines = dash_lines.add(Group::new()
+            .set("id", format!("pair-{}", n))
+            .set("class", "dash-pair")
+            .set("data-from", pair.node1.letter.as_str())
+            .set("data-to", pair.node2.letter.as_str())
+            .add(pair.get_shape(config)));
+    }
+    for (n, line) in lines.iter().enumerate() {
+        dash_lines = dash_lines.add(Group::new()
+            .set("id", format!("line-{}", n))
+            .set("class", "dash-line")
+            .set("data-from", line.node.letter.as_str())
+            .add(line.get_shape(config)));
+    }
+    (create_svg(view, phrase, config),dash_lines)
+}
+
+//`template`, or `default` when it is empty, with "{phrase}" filled in
+fn fill_phrase(template:&str, phrase:&str, default:&str) -> String {
+    let template = if template.is_empty() {default} else {template};
+    template.replace("{phrase}", phrase)
+}
+
+//Line art: each run of the pen as one stroked path, with the metadata so it still decodes
+pub fn render_line_art(sent:&GallSentence, strokes:Vec<Stroke>, view:ViewBox, config:&Config) -> Document {
+    let paths = plot::pen_paths(strokes, (view.0, view.1), config.pen_width/2.0);
+    let mut group = Group::new()
+        .set("id", "line-art")
+        .set("class", "line-art")
+        .set("fill", "none")
+        .set("stroke", config.pen_colour.as_str())
+        .set("stroke-width", config.pen_width)
+        .set("stroke-linecap", "round")
+        .set("stroke-linejoin", "round");
+    for path in &paths {
+        group = group.add(Path::new().set("d", pen_data(path)));
     }
-    for line in lines {
-        line.post_render(&mut post_render)
+    create_svg(view, &sent.phrase, config)
+        .add(sent.meta.to_element())
+        .add(group)
+}
+
+fn pen_data(path:&PenPath) -> Data {
+    let mut data = Data::new().move_to(path.start);
+    let mut pen = path.start;
+    for segment in &path.segments {
+        data = match *segment {
+            Segment::Line(end) => data.line_to(end),
+            Segment::Arc{centre, end, clockwise} => {
+                let (radius, large) = plot::arc_size(pen, centre, end, clockwise);
+                data.elliptical_arc_to((radius, radius, 0, if large {1} else {0}, if clockwise {1} else {0}, end.0, end.1))
+            },
+            Segment::Quad(control, end) => data.quadratic_curve_to((control.0, control.1, end.0, end.1)),
+        };
+        pen = segment.end();
     }
-    (create_svg(),post_render)
+    data
 }
 
-pub fn render_start<T:Renderable>(start_obj:T, drawn:Document) -> Document {
-    start_obj.render(drawn)
+pub fn render_start<T:Renderable>(start_obj:T, drawn:Document, config:&Config) -> Result<Document, Error> {
+    start_obj.render(drawn, config)
 }
-pub fn render_post(post_render:Vec<Element>, mut drawn: Document) -> Document {
-    for differed in post_render {
-        drawn = drawn.add(differed);
-    }  
-    drawn
+pub fn render_post(dash_lines:Group, drawn: Document) -> Document {
+    drawn.add(dash_lines)
+}
+
+fn classed<T:Into<Element>>(shape:T, class:&str) -> Element {
+    let mut shape = shape.into();
+    shape.assign("class", class);
+    shape
 }
 
 impl Renderable for GallSentence {
-    fn render(self, mut drawn:Document) -> Document {
+    fn render(self, drawn:Document, config:&Config) -> Result<Document, Error> {
+        let mut group = Group::new()
+            .set("id", "sentence")
+            .set("class", "sentence")
+            .set("data-text", self.phrase.as_str());
         let circle = Circle::new()
+            .set("class", "sentence-ring")
             .set("fill", "none")
-            .set("stroke", Config::SENT_SKEL_COLOUR())
+            .set("stroke", config.sent_skel_colour.as_str())
             .set("stroke-width", 2.0*self.thick())
             .set("cx", self.x())
             .set("cy", self.y())
             .set("r", self.radius());
         let filled_circle = Circle::new()
-            .set("fill", Config::SENT_COLOUR())
+            .set("class", "sentence-fill")
+            .set("fill", config.sent_colour.as_str())
             .set("stroke", "none")
             .set("cx", self.x())
             .set("cy", self.y())
             .set("r", self.inner_radius());
-        drawn = drawn.add(filled_circle);
+        group = group.add(filled_circle);
         for word in self.words.into_iter() {
-            drawn = word.render(drawn);
+            group = group.add(word.group(config)?);
         }
-        drawn.add(circle)
+        group = group.add(circle);
+        for (n, mark) in self.marks.iter().enumerate() {
+            let mut punct = Group::new()
+                .set("id", format!("punct-{}", n))
+                .set("class", format!("punct punct-{}", mark.punct.name()));
+            for shape in mark.get_shapes(config) {
+                punct = punct.add(shape);
+            }
+            group = group.add(punct);
+        }
+        Ok(drawn.add(self.meta.to_element()).add(group))
+    }
+}
+
+impl GallMark {
+    fn get_shapes(&self, config:&Config) -> Vec<Element> {
+        let line = self.parent_thick();
+        self.parts().into_iter().map(|part| match part {
+            MarkPart::Ring((x,y), r) => Circle::new()
+                .set("fill", config.sent_colour.as_str())
+                .set("stroke", config.sent_skel_colour.as_str())
+                .set("stroke-width", line)
+                .set("cx", x)
+                .set("cy", y)
+                .set("r", r)
+                .into(),
+            MarkPart::Dot((x,y), r) => Circle::new()
+                .set("fill", config.sent_skel_colour.as_str())
+                .set("stroke", "none")
+                .set("cx", x)
+                .set("cy", y)
+                .set("r", r)
+                .into(),
+            MarkPart::Tick((x1,y1), (x2,y2)) => Line::new()
+                .set("stroke", config.sent_skel_colour.as_str())
+                .set("stroke-width", line)
+                .set("x1", x1)
+                .set("y1", y1)
+                .set("x2", x2)
+                .set("y2", y2)
+                .into(),
+        }).collect()
     }
 }
 
 impl Renderable for GallWord {
-    fn render(self, mut drawn:Document) -> Document {
+    fn render(self, drawn:Document, config:&Config) -> Result<Document, Error> {
+        Ok(drawn.add(self.group(config)?))
+    }
+}
+
+impl GallWord {
+    //The word's skeleton, then one <g> per tainer holding one <g> per letter
+    fn group(self, config:&Config) -> Result<Group, Error> {
+        let mut group = Group::new()
+            .set("id", self.id.as_str())
+            .set("class", "word")
+            .set("data-word", self.text.as_str());
+        if config.accessible {
+            group = group
+                .set("aria-label", self.text.as_str())
+                .add(Title::new(self.text.as_str()));
+        }
         let radius = (self.inner_radius(),self.outer_radius());
         let circle = Circle::new()
+            .set("class", "word-skeleton")
             .set("fill", "none")
-            .set("stroke", Config::SKEL_COLOUR())
+            .set("stroke", config.skel_colour.as_str())
             .set("stroke-width", self.thick()*2.0)
             .set("cx", self.x())
             .set("cy", self.y())
             .set("r", self.radius());
+        let word_id = self.id.clone();
         let (skel, divot, mark) = self.pre_render();
-        drawn = if skel.len() == 0 {
-            drawn.add(circle)
+        group = if skel.is_empty() {
+            group.add(circle)
         } else {
-            GallWord::skel_render(skel, radius, drawn).unwrap()
+            GallWord::skel_render(skel, radius, &word_id, group, config)?
         };
-        for tainer in divot {
-            drawn = tainer.render(drawn);
-        }
-        for tainer in mark {
-            drawn = tainer.render(drawn);
+        for (n, tainer) in divot.into_iter().chain(mark) {
+            group = group.add(tainer.group(tainer_id(&word_id, n), Vec::new(), config));
         }
-        drawn
+        Ok(group)
     }
-}
-
-impl GallWord {
-    fn pre_render(self) -> (Vec<GallTainer>,Vec<GallTainer>,Vec<GallTainer>) {
+    //Tainers sorted by how they are drawn, each with its place in the word
+    fn pre_render(self) -> (Vec<Placed>,Vec<Placed>,Vec<Placed>) {
         let mut skel = Vec::new();
         let mut divot = Vec::new();
         let mut mark = Vec::new();
-        for tainer in self.tainer_vec {
-            match tainer.stem_type() {
+        for (n, tainer) in self.tainer_vec.into_iter().enumerate() {
+            let tainer = (n, tainer);
+            match tainer.1.stem_type() {
                 None => {
-                    if tainer.vowel.is_empty() {
+                    if tainer.1.vowel.is_empty() {
                         mark.push(tainer)
                     } else {
                         divot.push(tainer)
@@ -157,21 +306,22 @@ impl GallWord {
         }
         (skel,divot,mark)
     }
-    fn skel_render(skel:Vec<GallTainer>, radius:(f64,f64), mut drawn:Document) -> Result<Document, Error> {
-        let (mut data,inner_join, outer_join, init_angles) = skel[0].part_init();
+    fn skel_render(skel:Vec<Placed>, radius:(f64,f64), word_id:&str, mut group:Group, config:&Config) -> Result<Group, Error> {
+        let (mut data,inner_join, outer_join, init_angles) = skel[0].1.part_init()?;
         let mut fin_ang: (f64,f64) = init_angles;
         let mut post_render = Vec::new();
-        for tainer in skel {
+        for (n, tainer) in skel {
             (data, fin_ang) = tainer.part_render(data, fin_ang)?;
-            if !tainer.stem.is_empty() || !tainer.mark.is_empty() {
+            let mut cuts = Vec::new();
+            if !tainer.stem.is_empty() {
                 match tainer.stem_type(){
-                    Some(&StemType::B) => tainer.b_stack_render(&mut post_render),
-                    Some(&StemType::S) => tainer.t_stack_render(&mut post_render), // render skel letter gaps
+                    Some(&StemType::B) => tainer.b_stack_render(&mut cuts, config)?,
+                    Some(&StemType::S) => tainer.t_stack_render(&mut cuts, config)?, // render skel letter gaps
                     Some(_) => {},
                     None => {}, //render skel marks
                 }
             }
-            tainer.post_render(&mut post_render); //render non-skel stems
+            post_render.push(tainer.group(tainer_id(word_id, n), cuts, config)); //render non-skel stems
         };
         let (inner_sweep, outer_sweep) = (
             (TAU + init_angles.0 - fin_ang.0) >= PI,
@@ -192,48 +342,82 @@ impl GallWord {
             outer_join.0, outer_join.1
         )).close();
         let inner_path = Path::new()
+            .set("class", "word-fill")
             .set("d", closed_inner_loop);
         let outer_path = Path::new()
+            .set("class", "word-skeleton")
             .set("d", closed_outer_loop);
-        drawn = drawn.add(outer_path
-            .set("fill", Config::SKEL_COLOUR())
+        group = group.add(outer_path
+            .set("fill", config.skel_colour.as_str())
             .set("stroke-width", 0.0)
             .set("stroke", "none")
         );  
-        drawn = drawn.add(inner_path
-            .set("fill", Config::WRD_COLOUR())
+        group = group.add(inner_path
+            .set("fill", config.wrd_colour.as_str())
             .set("stroke-width", 0.0)
             .set("stroke", "none")
         );
         for differed in post_render {
-            drawn = drawn.add(differed);
+            group = group.add(differed);
         }  
-        Ok(drawn)
+        Ok(group)
     }
 }
 
-impl Renderable for GallTainer {
-    fn render(self, mut drawn:Document) -> Document {
-        for stem in self.stem {
-            drawn = stem.render(drawn);
-        }
-        for vow in self.vowel {
-            drawn = vow.render(drawn);
-        }
-        for dot in self.dot {
-            drawn = dot.render(drawn);
-        }
-        if Config::NODE_VISIBILITY {
-            for node in self.node {
-                drawn = node.render(drawn);
+//A tainer and its index in the word
+type Placed = (usize,GallTainer);
+
+fn tainer_id(word_id:&str, tainer:usize) -> String {
+    format!("{}-t{}", word_id, tainer)
+}
+
+impl GallTainer {
+    //One <g> per letter inside one for the tainer. `cuts` are the stack
+    //gaps of B and S stems, each with the index of its stem.
+    fn group(&self, id:String, cuts:Vec<(usize,Element)>, config:&Config) -> Group {
+        let mut group = Group::new()
+            .set("id", id)
+            .set("class", "tainer");
+        for span in &self.letters {
+            let mut letter = Group::new()
+                .set("id", span.id.as_str())
+                .set("class", span.class.as_str())
+                .set("data-letter", span.text.as_str());
+            for n in span.stem.clone() {
+                if let Some(circle) = self.stem[n].get_shape(config) {
+                    let mut ring = classed(circle, "stem-ring");
+                    //digit rings are built as J stems, but drawn in their own colour
+                    if self.is_number() {
+                        ring.assign("stroke", config.digit_colour.as_str());
+                    }
+                    letter = letter.add(ring);
+                }
+            }
+            for (_, cut) in cuts.iter().filter(|(n, _)| span.stem.contains(n)) {
+                letter = letter.add(classed(cut.clone(), "stem-cut"));
+            }
+            for vow in &self.vowel[span.vowel.clone()] {
+                letter = letter.add(classed(vow.get_shape(config), "vowel-ring"));
             }
+            for dot in &self.dot[span.dot.clone()] {
+                letter = letter.add(classed(dot.get_shape(config), "dot"));
+            }
+            for line in &self.line[span.line.clone()] {
+                letter = letter.add(classed(line.get_shape(config), "digit-line"));
+            }
+            if config.node_visibility {
+                for node in &self.node[span.node.clone()] {
+                    letter = letter.add(classed(node.get_shape(config), "node"));
+                }
+            }
+            group = group.add(letter);
         }
-        drawn
+        group
     }
 }
 
 impl SkelPart for GallTainer {
-    fn part_render(&self, inner_outer:(Data,Data), start_ang:(f64,f64)) -> Result<((Data,Data),(f64,f64)), Error> {
+    fn part_render(&self, inner_outer:SkelData, start_ang:(f64,f64)) -> Result<(SkelData,(f64,f64)), Error> {
         let (stem1, stem2) = self.stack_check()?;
         let (thi_inner,thi_outer) = self.thi_calc()?;
         let (theta_inner,theta_outer) = self.theta_calc()?;
@@ -244,8 +428,8 @@ impl SkelPart for GallTainer {
         let (big_inner_l_arc, big_outer_l_arc) = (
             2.0*theta_inner < PI, 2.0*theta_outer < PI);
         let (inner_word_end_angle, outer_word_end_angle) = (
-            stem1.ang().unwrap() - thi_inner, 
-            stem2.ang().unwrap() - thi_outer
+            stem1.ang().ok_or(Error::new(GallError::AngleUndefined))? - thi_inner, 
+            stem2.ang().ok_or(Error::new(GallError::AngleUndefined))? - thi_outer
         );
         let (long_inner_skeleton, long_outer_skeleton) = (
             (inner_word_end_angle - start_ang.0).abs() > PI,
@@ -258,11 +442,11 @@ impl SkelPart for GallTainer {
         );
         let inner_letter_start = tracker.svg_ord();
         let inner_letter_finish = tracker.compute_loc(2.0 * thi_inner);
-        let final_in_ang = tracker.ang().unwrap();
+        let final_in_ang = tracker.ang().ok_or(Error::new(GallError::AngleUndefined))?;
         tracker.mut_ang_d(w_ou_rad, outer_word_end_angle);
         let outer_letter_start = tracker.svg_ord();
         let outer_letter_finish = tracker.compute_loc(2.0 * thi_outer);
-        let final_ou_ang = tracker.ang().unwrap();
+        let final_ou_ang = tracker.ang().ok_or(Error::new(GallError::AngleUndefined))?;
         // x radius, y radius, rotation, large arc, sweep direction, end x, end y
         let inner_data = inner_outer.0.elliptical_arc_to((
             w_in_rad, w_in_rad, 
@@ -295,12 +479,12 @@ impl SkelPart for GallTainer {
             (final_in_ang,final_ou_ang),
         ))
     }
-    fn part_init(&self) -> ((Data, Data),(f64,f64),(f64,f64), (f64,f64)) {
-        let (stem1, stem2) = self.stack_check().expect("Tainer is empty"); 
-        let (thi_inner,thi_outer) = (stem1.inner_thi().expect(""),stem2.outer_thi().expect(""));
+    fn part_init(&self) -> Result<SkelInit, Error> {
+        let (stem1, stem2) = self.stack_check()?; 
+        let (thi_inner,thi_outer) = (stem1.inner_thi()?,stem2.outer_thi()?);
         let (inner_init_angle, outer_init_angle) = (
-            0.0_f64.min(stem1.ang().unwrap() - thi_inner),
-            0.0_f64.min(stem2.ang().unwrap() - thi_outer)
+            0.0_f64.min(stem1.ang().ok_or(Error::new(GallError::AngleUndefined))? - thi_inner),
+            0.0_f64.min(stem2.ang().ok_or(Error::new(GallError::AngleUndefined))? - thi_outer)
         );
         let mut tracker = GallLoc::new(
             inner_init_angle,
@@ -310,7 +494,7 @@ impl SkelPart for GallTainer {
         let inner_continuum = tracker.pos_ref().get();
         tracker.mut_ang_d(stem2.parent_outer(), outer_init_angle);
         let outer_continuum = tracker.pos_ref().get();
-        (
+        Ok((
             (
                 Data::new().move_to(inner_continuum),
                 Data::new().move_to(outer_continuum),
@@ -321,29 +505,12 @@ impl SkelPart for GallTainer {
                 inner_init_angle,
                 outer_init_angle,
             )
-        )
+        ))
     }
 }
-impl FreeRender for GallTainer {
-    fn post_render(&self, vec: &mut Vec<Element>) {
-        for stem in &self.stem {
-            stem.post_render(vec);
-        }
-        for vow in &self.vowel {
-            vow.post_render(vec);
-        }
-        for dot in &self.dot {
-            dot.post_render(vec);
-        }
-        for node in &self.node {
-            node.post_render(vec);
-        }
-    } 
-}
-
 impl GallTainer {
-    fn t_stack_render(&self, vec: &mut Vec<Element>) {
-        let stem = self.stem.first().expect("There should be more than 1 stem");
+    fn t_stack_render(&self, vec: &mut Vec<(usize,Element)>, config:&Config) -> Result<(), Error> {
+        let stem = self.stem.first().ok_or(Error::new(GallError::NoStemInTainer))?;
         let ang = self.ang();
         let dist = stem.parent_inner();
         let mut tracker = GallLoc::new(
@@ -352,13 +519,13 @@ impl GallTainer {
             stem.get_center()
         );
         let mut first = true;
-        let (thi2, theta2) = (stem.inner_thi2().unwrap(), stem.inner_theta2().unwrap());
+        let (thi2, theta2) = (stem.inner_thi2()?, stem.inner_theta2()?);
         tracker.mut_ang(ang - thi2);
         let mut pos1 = tracker.pos_ref().get();
         tracker.mut_ang(ang + thi2);
         let pos2 = tracker.pos_ref().get();
         let mut path = Path::new()
-            .set("fill", Config::WRD_COLOUR())
+            .set("fill", config.wrd_colour.as_str())
             .set("stroke-width", 0.0)
             .set("stroke", "none");
         let mut data = Data::new()
@@ -370,16 +537,16 @@ impl GallTainer {
                 1,
                 pos2.0, pos2.1,
             ));
-        for stem in &self.stem {
+        for (n, stem) in self.stem.iter().enumerate() {
             if first {
                 first = false;
                 continue;
             }
             let (thi, thi2, theta, theta2) = (
-                stem.inner_thi().unwrap(), 
-                stem.inner_thi2().unwrap(), 
-                stem.inner_theta().unwrap(), 
-                stem.inner_theta2().unwrap()
+                stem.inner_thi()?, 
+                stem.inner_thi2()?, 
+                stem.inner_theta()?, 
+                stem.inner_theta2()?
             );
             tracker.mut_ang(ang + thi);
             let pos3 = tracker.pos_ref().get();
@@ -407,13 +574,13 @@ impl GallTainer {
                 //.line_to(pos1)
                 .close();
             path = path.set("d", data);
-            vec.push(path.into());
+            vec.push((n, path.into()));
             tracker.mut_ang(ang - thi2);
             pos1 = tracker.pos_ref().get();
             tracker.mut_ang(ang + thi2);
             let pos2 = tracker.pos_ref().get();
             path = Path::new()
-                .set("fill", Config::WRD_COLOUR())
+                .set("fill", config.wrd_colour.as_str())
                 .set("stroke-width", 0.0)
                 .set("stroke", "none");
             data = Data::new().move_to(pos1).elliptical_arc_to((
@@ -424,9 +591,10 @@ impl GallTainer {
                 pos2.0, pos2.1,
             ));
         }
+        Ok(())
     }
-    fn b_stack_render(&self, vec: &mut Vec<Element>) {
-        let stem = self.stem.first().expect("There should be more than 1 stem");
+    fn b_stack_render(&self, vec: &mut Vec<(usize,Element)>, config:&Config) -> Result<(), Error> {
+        let stem = self.stem.first().ok_or(Error::new(GallError::NoStemInTainer))?;
         let ang = self.ang();
         let dist = stem.parent_outer();
         let mut tracker = GallLoc::new(
@@ -435,16 +603,16 @@ impl GallTainer {
             stem.get_center()
         );
         let mut first = true;
-        for stem in &self.stem {
+        for (n, stem) in self.stem.iter().enumerate() {
             if first {
                 first = false;
                 continue;
             }
             let (thi, thi2, theta, theta2) = (
-                stem.outer_thi().unwrap(),
-                stem.outer_thi2().unwrap(), 
-                stem.outer_theta().unwrap(),
-                stem.outer_theta2().unwrap()
+                stem.outer_thi()?,
+                stem.outer_thi2()?, 
+                stem.outer_theta()?,
+                stem.outer_theta2()?
             );
             tracker.mut_ang(ang - thi2);
             let pos1 = tracker.pos_ref().get();
@@ -478,38 +646,32 @@ impl GallTainer {
                     pos1.0, pos1.1,
                 )).close();
             let path = Path::new()
-                .set("fill",Config::SENT_SKEL_COLOUR())
+                .set("fill",config.sent_skel_colour.as_str())
                 .set("stroke-width", 0.0)
                 .set("stroke", "none")
                 .set("d", data);
-            vec.push(path.into());
+            vec.push((n, path.into()));
         }
+        Ok(())
     }
 }
 
 impl Renderable for Stem {
-    fn render(self, drawn:Document) -> Document {
-        match self.get_shape() {
-            Some(circle) => drawn.add(circle),
-            None => drawn
+    fn render(self, drawn:Document, config:&Config) -> Result<Document, Error> {
+        match self.get_shape(config) {
+            Some(circle) => Ok(drawn.add(circle)),
+            None => Ok(drawn)
         }
     }
 }
 
-impl FreeRender for Stem {
-    fn post_render(&self, vec:&mut Vec<Element>) {
-        if let Some(circle) = self.get_shape() {
-            vec.push(circle)
-        }
-    }
-}
 impl Stem {
-    fn get_shape(&self) -> Option<Element> {
+    fn get_shape(&self, config:&Config) -> Option<Element> {
         match self.stem_type {
             StemType::J|StemType::Z => {
                 let circle = Circle::new()
                     .set("fill", "none")
-                    .set("stroke", Config::JZ_COLOUR())
+                    .set("stroke", config.jz_colour.as_str())
                     .set("stroke-width", (self.thick()*2.0).to_string()+"px")
                     .set("cx", self.x())
                     .set("cy", self.y())
@@ -523,10 +685,10 @@ impl Stem {
 }
 
 impl Basic for GallVowel {
-    fn get_shape(&self) -> Element {
+    fn get_shape(&self, config:&Config) -> Element {
         Circle::new()
             .set("fill", "none")
-            .set("stroke", Config::VOW_COLOUR())
+            .set("stroke", config.vow_colour.as_str())
             .set("stroke-width", (self.thick()*2.0).to_string()+"px")
             .set("cx", self.x())
             .set("cy", self.y())
@@ -536,9 +698,9 @@ impl Basic for GallVowel {
 }
 
 impl Basic for Dot {
-    fn get_shape(&self) -> Element {
+    fn get_shape(&self, config:&Config) -> Element {
         Circle::new()
-            .set("fill", Config::DOT_COLOUR())
+            .set("fill", config.dot_colour.as_str())
             .set("stroke", "none")
             .set("cx", self.x())
             .set("cy", self.y())
@@ -547,43 +709,68 @@ impl Basic for Dot {
     }
 }
 
-impl Basic for GallLine<'_> {
-    fn get_shape(&self) -> Element {
-        let (x,y) = self.get_endpoint();
+impl Basic for DigitLine {
+    fn get_shape(&self, config:&Config) -> Element {
+        let ((x1,y1),(x2,y2)) = self.ends();
         Line::new()
-            .set("stroke", Config::SKEL_COLOUR())
-            .set("stroke-width", self.thickness*2)
-            .set("x1", self.node.x())
-            .set("y1", self.node.y())
-            .set("x2", x)
-            .set("y2", y)
+            .set("stroke", config.digit_colour.as_str())
+            .set("stroke-width", self.thickness*2.0)
+            .set("x1", x1)
+            .set("y1", y1)
+            .set("x2", x2)
+            .set("y2", y2)
             .into()
     }
 }
 
+impl Basic for GallLine<'_> {
+    fn get_shape(&self, config:&Config) -> Element {
+        connector_shape((self.node.x(),self.node.y()), self.get_endpoint(), self.bend, self.thickness, config)
+    }
+}
+
 impl Basic for GallLinePair<'_> {
-    fn get_shape(&self) -> Element {
-        Line::new()
-            .set("stroke", Config::SKEL_COLOUR())
-            .set("stroke-width", self.thickness*2)
-            .set("x1", self.node1.x())
-            .set("y1", self.node1.y())
-            .set("x2", self.node2.x())
-            .set("y2", self.node2.y())
-            .into()
+    fn get_shape(&self, config:&Config) -> Element {
+        let start = (self.node1.x(),self.node1.y());
+        let end = (self.node2.x(),self.node2.y());
+        connector_shape(start, end, self.bend, self.thickness, config)
+    }
+}
+
+fn connector_shape(start:(f64,f64), end:(f64,f64), bend:Option<(f64,f64)>, thickness:i16, config:&Config) -> Element {
+    match bend {
+        Some(control) => {
+            let data = Data::new()
+                .move_to(start)
+                .quadratic_curve_to((control.0, control.1, end.0, end.1));
+            Path::new()
+                .set("fill", "none")
+                .set("stroke", config.skel_colour.as_str())
+                .set("stroke-width", thickness*2)
+                .set("d", data)
+                .into()
+        },
+        None => Line::new()
+            .set("stroke", config.skel_colour.as_str())
+            .set("stroke-width", thickness*2)
+            .set("x1", start.0)
+            .set("y1", start.1)
+            .set("x2", end.0)
+            .set("y2", end.1)
+            .into(),
     }
 }
 
 //------ TOGGLE NODE_VISIBILITY in Config ------
 
 impl Basic for GallNode {
-    fn get_shape(&self) -> Element {
+    fn get_shape(&self, config:&Config) -> Element {
         Circle::new()
-            .set("fill", Config::DEBUG_COLOUR())
+            .set("fill", config.debug_colour.as_str())
             .set("stroke", "none")
             .set("cx", self.x())
             .set("cy", self.y())
-            .set("r", Config::DOT_RADIUS*0.8)
+            .set("r", config.dot_radius*0.8)
             .into()
     }
 }
\ No newline at end of file
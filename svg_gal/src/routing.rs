@@ -0,0 +1,161 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gall_circle::{Circle, HollowCircle};
+use crate::gall_loc::Location;
+use crate::gall_node::GallNode;
+use crate::gall_sentence::GallSentence;
+
+type Point = (f64,f64);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectorMode {
+    //Obstructed connectors are re-angled, or re-paired when no angle clears
+    //them, so a line only runs through letters when no other pairing can avoid it
+    #[default]
+    Straight,
+    //Obstructed connectors are also bent into a quadratic Bezier before
+    //falling back to re-pairing
+    Curved,
+}
+
+//Which word, and which tainer within it (None for the word ring itself), a
+//piece of geometry belongs to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Owner {
+    pub word: usize,
+    pub tainer: Option<usize>,
+}
+
+//Everything drawn as a circle is treated as a solid disc
+#[derive(Clone, Copy, Debug)]
+pub struct Obstacle {
+    pub center: Point,
+    pub radius: f64,
+    pub owner: Owner,
+}
+
+//Obstacles of a sentence, plus the owner of each node keyed by its position cell
+#[derive(Default)]
+pub struct Obstacles {
+    pub list: Vec<Obstacle>,
+    nodes: Vec<(Rc<Cell<Point>>, Owner)>,
+}
+
+//Bend offsets tried, as fractions of the connector length
+const BEND_STEPS: [f64; 8] = [0.1, 0.2, 0.3, 0.45, 0.6, 0.8, 1.0, 1.25];
+//Where along the connector the bend is pulled from, the middle first, then
+//nearer one end or the other to get round something close to it
+const BEND_ALONG: [f64; 5] = [0.5, 0.3, 0.7, 0.15, 0.85];
+const CURVE_SAMPLES: usize = 16;
+
+impl Obstacles {
+    pub fn from_sentence(sent:&GallSentence) -> Obstacles {
+        let mut obstacles = Obstacles::default();
+        for (word_num, word) in sent.words.iter().enumerate() {
+            obstacles.list.push(Obstacle {
+                center: word.svg_ord(),
+                radius: word.radius(),
+                owner: Owner { word: word_num, tainer: None },
+            });
+            for (tainer_num, tainer) in word.tainer_vec.iter().enumerate() {
+                let owner = Owner { word: word_num, tainer: Some(tainer_num) };
+                let stems = tainer.stem.iter().map(|stem| (stem.svg_ord(), stem.outer_radius()));
+                let vowels = tainer.vowel.iter().map(|vow| (vow.svg_ord(), vow.outer_radius()));
+                let dots = tainer.dot.iter().map(|dot| (dot.svg_ord(), dot.radius()));
+                for (center, radius) in stems.chain(vowels).chain(dots) {
+                    obstacles.list.push(Obstacle { center, radius, owner });
+                }
+                obstacles.nodes.extend(tainer.node.iter().map(|node| (node.pos_ref(), owner)));
+            }
+        }
+        obstacles
+    }
+    pub fn owner(&self, node:&GallNode) -> Option<Owner> {
+        let pos = node.pos_ref();
+        self.nodes.iter().find(|(cell, _)| Rc::ptr_eq(cell, &pos)).map(|&(_, owner)| owner)
+    }
+    //An obstacle blocks unless it belongs to one of the connector's own letters.
+    //A word ring only blocks connectors that start and end outside that word.
+    fn blocks(obstacle:&Obstacle, ends:&[Owner]) -> bool {
+        match obstacle.owner.tainer {
+            Some(_) => !ends.contains(&obstacle.owner),
+            None => ends.iter().all(|end| end.word != obstacle.owner.word),
+        }
+    }
+    //Number of obstacles the polyline passes through, each counted once.
+    //Obstacles already covering an end can't be avoided and are skipped.
+    pub fn path_hits(&self, points:&[Point], ends:&[Owner], margin:f64) -> usize {
+        let (Some(&first), Some(&last)) = (points.first(), points.last()) else {return 0};
+        let inside = |obstacle:&Obstacle, point:Point| {
+            (point.0-obstacle.center.0).hypot(point.1-obstacle.center.1) < obstacle.radius
+        };
+        //box around the path, anything further out than its radius can't touch it
+        let (mut low, mut high) = (first, first);
+        for point in points {
+            low = (low.0.min(point.0), low.1.min(point.1));
+            high = (high.0.max(point.0), high.1.max(point.1));
+        }
+        let near = |obstacle:&Obstacle| {
+            let reach = obstacle.radius + margin;
+            obstacle.center.0 > low.0 - reach && obstacle.center.0 < high.0 + reach &&
+            obstacle.center.1 > low.1 - reach && obstacle.center.1 < high.1 + reach
+        };
+        self.list.iter()
+            .filter(|obstacle| near(obstacle))
+            .filter(|obstacle| Obstacles::blocks(obstacle, ends))
+            .filter(|obstacle| !inside(obstacle, first) && !inside(obstacle, last))
+            .filter(|obstacle| points.windows(2).any(|seg| {
+                seg_dist(obstacle.center, seg[0], seg[1]) < obstacle.radius + margin
+            }))
+            .count()
+    }
+    pub fn seg_hits(&self, start:Point, end:Point, ends:&[Owner], margin:f64) -> usize {
+        self.path_hits(&[start, end], ends, margin)
+    }
+    //Control point of the least obstructed bend, if it beats the straight line.
+    //Smaller bends are tried first, so the first clear one is also the gentlest.
+    pub fn bend(&self, start:Point, end:Point, ends:&[Owner], margin:f64) -> Option<Point> {
+        let mut best_hits = self.seg_hits(start, end, ends, margin);
+        let mut best = None;
+        let (dx, dy) = (end.0-start.0, end.1-start.1);
+        for step in BEND_STEPS {
+            for along in BEND_ALONG {
+                for side in [1.0, -1.0] {
+                    if best_hits == 0 {
+                        return best
+                    }
+                    //perpendicular offset from a point on the line, scaled with the length
+                    let control = (start.0 + along*dx - side*step*dy, start.1 + along*dy + side*step*dx);
+                    let hits = self.path_hits(&quad_points(start, control, end), ends, margin);
+                    if hits < best_hits {
+                        best_hits = hits;
+                        best = Some(control);
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+pub fn quad_points(start:Point, control:Point, end:Point) -> Vec<Point> {
+    (0..=CURVE_SAMPLES).map(|num| {
+        let t = num as f64/CURVE_SAMPLES as f64;
+        let (a, b, c) = ((1.0-t)*(1.0-t), 2.0*t*(1.0-t), t*t);
+        (a*start.0 + b*control.0 + c*end.0, a*start.1 + b*control.1 + c*end.1)
+    }).collect()
+}
+
+//Shortest distance from a point to the segment a-b
+fn seg_dist(point:Point, a:Point, b:Point) -> f64 {
+    let (dx, dy) = (b.0-a.0, b.1-a.1);
+    let len_sq = dx*dx + dy*dy;
+    let t = if len_sq > 0.0 {
+        (((point.0-a.0)*dx + (point.1-a.1)*dy)/len_sq).clamp(0.0, 1.0)
+    } else {0.0};
+    (point.0 - a.0 - t*dx).hypot(point.1 - a.1 - t*dy)
+}